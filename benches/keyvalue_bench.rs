@@ -6,7 +6,7 @@ fn keyvalue_fullcopy(data: &str) {
 }
 
 fn keyvalue_zerocopy(data: &str) {
-    let _parser = key_value_parser::zero_copy::Parser::new(data).unwrap();
+    let _parser = zero_copy::Parser::new(data).unwrap();
 }
 
 fn keyvalue_almost_zerocopy(data: &str) {
@@ -68,7 +68,7 @@ fn criterion_benchmark_quote(c: &mut Criterion) {
         b.iter(|| keyvalue_full_almost_zerocopy(&data))
     });
     c.bench_function("keyvalue_zero_parse", |b| {
-        b.iter(|| keyvalue_zero_parse(&data,&keys))
+        b.iter(|| keyvalue_zero_parse(&data, &keys))
     });
 
     // create test data.  1000 key/value pairs where each key and value is 1000 characters long
@@ -78,11 +78,7 @@ fn criterion_benchmark_quote(c: &mut Criterion) {
     for i in 0..1000 {
         let key = format!("{}{i}", "k".repeat(1000));
         keys.push(key.clone());
-        data.push_str(&format!(
-            "{}=\"{}{i}\" ",
-            key,
-            "v".repeat(1000)
-        ));
+        data.push_str(&format!("{}=\"{}{i}\" ", key, "v".repeat(1000)));
     }
 
     c.bench_function("keyvalue_fullcopy_1000_1000", |b| {
@@ -92,7 +88,7 @@ fn criterion_benchmark_quote(c: &mut Criterion) {
         b.iter(|| keyvalue_full_almost_zerocopy(&data))
     });
     c.bench_function("keyvalue_zero_parse_1000_1000", |b| {
-        b.iter(|| keyvalue_zero_parse(&data,&keys))
+        b.iter(|| keyvalue_zero_parse(&data, &keys))
     });
 
     // create test data.  1000 key/value pairs where each key and value is 1000 characters long with escape characters
@@ -116,13 +112,13 @@ fn criterion_benchmark_quote(c: &mut Criterion) {
         b.iter(|| keyvalue_full_almost_zerocopy(&data))
     });
     c.bench_function("keyvalue_zero_parse_1000_1000_escaped", |b| {
-        b.iter(|| keyvalue_zero_parse(&data,&keys))
+        b.iter(|| keyvalue_zero_parse(&data, &keys))
     });
 }
 
 criterion_group!(
     benches,
-    //criterion_benchmark_nonquote,
+    criterion_benchmark_nonquote,
     criterion_benchmark_quote
 );
 criterion_main!(benches);
@@ -1,24 +1,28 @@
-use anyhow::Result;
-use nom::{
-    bytes::complete::{tag, take, take_while},
-    character::complete::multispace0,
-    IResult,
-};
-use std::collections::HashMap;
-
-#[allow(dead_code)]
-enum StringOrStr<'a> {
-    String(String),
-    Str(&'a str),
-}
-impl<'a> From<&'a str> for StringOrStr<'a> {
-    fn from(value: &'a str) -> Self {
-        StringOrStr::Str(value)
-    }
-}
-
+use nom::bytes::complete::{tag, take_until};
+use std::collections::{hash_map, HashMap, HashSet};
+use std::ops::Index;
+
+use crate::config::ParserConfig;
+use crate::error::{expect, to_parse_error, NomResult, ParseError, Position};
+use crate::grammar::{self, ValueKind};
+use crate::{full_almost_zero_copy::StringOrStr, parser, KeyValueParser, Span};
+
+/// Iterator over the pairs of a [`Parser`], see [`Parser::iter`]
+pub type Iter<'s, 'a> = std::iter::Map<
+    hash_map::Iter<'s, &'a str, StringOrStr<'a>>,
+    fn((&'s &'a str, &'s StringOrStr<'a>)) -> (&'a str, &'s str),
+>;
+
+/// Parses key value pairs like [`zero_copy`](crate::zero_copy::Parser), keeping each value as
+/// a slice of the input.  Only a bare value that goes on to the next line under
+/// [`ParserConfig::line_continuation`] is copied, to join its lines.
+#[derive(Debug, Clone, Default)]
 pub struct Parser<'a> {
     map: HashMap<&'a str, StringOrStr<'a>>,
+    duplicates: HashMap<&'a str, Vec<StringOrStr<'a>>>,
+    flags: HashSet<&'a str>,
+    input: &'a str,
+    config: ParserConfig,
 }
 impl<'a> Parser<'a> {
     /// Construct a new parser.  
@@ -30,29 +34,140 @@ impl<'a> Parser<'a> {
     /// assert_eq!(parser.len(), 1);
     /// assert_eq!(parser.get("key").unwrap(), "value");
     /// ```
-    pub fn new(input: &'a str) -> Result<Self> {
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        Self::with_config(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration.
+    /// If the parser cannot parse the input, an error will be returned.
+    pub fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        let (parser, errors) = Self::parse(input, config, false);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(parser),
+        }
+    }
+
+    /// Construct a new parser that skips over pairs it cannot parse.
+    /// Every pair that fails to parse is skipped up to the next whitespace, and its error
+    /// is returned alongside the pairs that did parse.
+    /// ```
+    /// use key_value_parser::almost_zero_copy::Parser;
+    /// const DATA: &str = "one=1 ;two=2 three=3";
+    /// let (parser, errors) = Parser::new_lenient(DATA);
+    /// assert_eq!(parser.len(), 2);
+    /// assert_eq!(parser.get("three").unwrap(), "3");
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].offset(), 6);
+    /// ```
+    pub fn new_lenient(input: &'a str) -> (Self, Vec<ParseError>) {
+        Self::with_config_lenient(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration, that skips over pairs it cannot
+    /// parse.  See [`Parser::new_lenient`].
+    pub fn with_config_lenient(input: &'a str, config: &ParserConfig) -> (Self, Vec<ParseError>) {
+        Self::parse(input, config, true)
+    }
+
+    /// Parses the input, collecting every error.  Unless `lenient` is set, parsing stops
+    /// at the first error.
+    fn parse(input: &'a str, config: &ParserConfig, lenient: bool) -> (Self, Vec<ParseError>) {
         // use nom to parse data
         let mut map = HashMap::new();
+        let mut duplicates = HashMap::new();
+        let mut flags = HashSet::new();
+        let mut errors = Vec::new();
 
-        let mut head = input.trim_start();
+        let mut head = grammar::skip_space(input, config);
         while !head.is_empty() {
-            let (input, (key, value)) = parse_one_key_value(head)
-                .map_err(|e| anyhow::anyhow!("Could not parse input data: {:?}", e))?;
-
-            map.insert(key, value);
-
-            head = input;
+            match parse_one_key_value(head, config) {
+                Ok((rest, (key, None))) => {
+                    flags.insert(key);
+                    head = rest;
+                }
+                Ok((rest, (key, Some(value)))) => {
+                    let policy = config.duplicate_policy;
+                    if !grammar::insert(&mut map, &mut duplicates, policy, key, value) {
+                        let offset = grammar::offset_in(input, key);
+                        errors.push(ParseError::DuplicateKey(Position::new(input, offset)));
+                        if !lenient {
+                            break;
+                        }
+                    }
+
+                    head = rest;
+                }
+                Err(e) => {
+                    errors.push(to_parse_error(input, e));
+                    if !lenient {
+                        break;
+                    }
+
+                    head = grammar::skip_pair(head, config);
+                }
+            }
         }
 
-        Ok(Self { map })
+        (
+            Self {
+                map,
+                duplicates,
+                flags,
+                input,
+                config: config.clone(),
+            },
+            errors,
+        )
     }
 
     /// Gets a value from the container.  Same signature as HashMap::get
     pub fn get(&self, key: &str) -> Option<&str> {
-        self.map.get(key).map(|v| match v {
-            StringOrStr::String(s) => s.as_str(),
-            StringOrStr::Str(s) => s,
-        })
+        self.map.get(key).map(AsRef::as_ref)
+    }
+
+    /// Gets every value of a key, in the order they appear in the input.  Only
+    /// [`DuplicatePolicy::Collect`](crate::config::DuplicatePolicy::Collect) keeps more than
+    /// one value per key.
+    pub fn get_all<'s>(&'s self, key: &str) -> impl Iterator<Item = &'s str> {
+        self.map
+            .get(key)
+            .into_iter()
+            .chain(self.duplicates.get(key).into_iter().flatten())
+            .map(AsRef::as_ref)
+    }
+
+    /// Iterates over the key value pairs, in no particular order
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        self.map.iter().map(|(k, v)| (*k, v.as_ref()))
+    }
+
+    /// Iterates over the keys, in no particular order
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.map.keys().copied()
+    }
+
+    /// Iterates over the values, in no particular order
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.map.values().map(AsRef::as_ref)
+    }
+
+    /// Gets where the pair of a key sits in the input.  Pairs that were not parsed from the
+    /// input, such as those added through [`Extend`], have no span.
+    pub fn get_span(&self, key: &str) -> Option<Span> {
+        let (key, _) = self.map.get_key_value(key)?;
+        Span::of(self.input, key, &self.config)
+    }
+
+    /// Returns true if the key appeared on its own, without a value, under
+    /// [`ParserConfig::flags`](crate::config::ParserConfig::flags)
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.flags.contains(key)
+    }
+
+    /// Iterates over the keys that appeared on their own, in no particular order
+    pub fn flags(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.flags.iter().copied()
     }
 
     /// Returns how many key value pairs are available
@@ -66,46 +181,151 @@ impl<'a> Parser<'a> {
     }
 }
 
-fn parse_one_key_value(input: &str) -> IResult<&str, (&str, StringOrStr)> {
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, key) = take_while(|c: char| c.is_alphanumeric() || c == '-' || c == '_')(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, _) = tag("=")(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, value) = parse_value(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-
-    Ok((input, (key, value.into())))
+/// Parsers are equal if they hold the same pairs, whatever input they came from
+impl PartialEq for Parser<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map && self.duplicates == other.duplicates && self.flags == other.flags
+    }
 }
+impl Eq for Parser<'_> {}
 
-fn unquoted_value(input: &str) -> IResult<&str, &str> {
-    take_while(|c: char| !c.is_whitespace())(input)
+impl<'s, 'a> IntoIterator for &'s Parser<'a> {
+    type Item = (&'a str, &'s str);
+    type IntoIter = Iter<'s, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
-fn quoted_value(input: &str) -> IResult<&str, &str> {
-    let (input, _) = tag("\"")(input)?;
+impl<'a> IntoIterator for Parser<'a> {
+    type Item = (&'a str, StringOrStr<'a>);
+    type IntoIter = hash_map::IntoIter<&'a str, StringOrStr<'a>>;
 
-    // consume until we hit a quote
-    let (input, so_far) = take_while(|c: char| c != '"')(input)?;
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
 
-    let (input, _quote) = tag("\"")(input)?;
+/// Panics if the key is not present, like indexing a `HashMap`
+impl Index<&str> for Parser<'_> {
+    type Output = str;
 
-    Ok((input, so_far))
+    fn index(&self, key: &str) -> &str {
+        self.get(key).expect("key not found")
+    }
+}
+
+/// Later pairs replace earlier ones with the same key
+impl<'a> FromIterator<(&'a str, &'a str)> for Parser<'a> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, &'a str)>>(iter: I) -> Self {
+        let mut parser = Self::default();
+        parser.extend(iter);
+        parser
+    }
+}
+
+/// Later pairs replace earlier ones with the same key
+impl<'a> Extend<(&'a str, &'a str)> for Parser<'a> {
+    fn extend<I: IntoIterator<Item = (&'a str, &'a str)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            grammar::replace(&mut self.map, key, StringOrStr::from(value));
+        }
+    }
 }
 
-fn parse_value(input: &str) -> IResult<&str, &str> {
-    let (_, peek_next_char) = take(1usize)(input)?;
+impl<'a> KeyValueParser<'a> for Parser<'a> {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
+        Parser::new(input)
+    }
+
+    fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        Parser::with_config(input, config)
+    }
+
+    fn get(&self, key: &str) -> Option<StringOrStr<'_>> {
+        Parser::get(self, key).map(StringOrStr::Str)
+    }
+
+    fn get_span(&self, key: &str) -> Option<Span> {
+        Parser::get_span(self, key)
+    }
+
+    fn is_flag(&self, key: &str) -> bool {
+        Parser::is_flag(self, key)
+    }
+
+    fn len(&self) -> usize {
+        Parser::len(self)
+    }
 
-    match peek_next_char {
-        "\"" => quoted_value(input),
-        _ => unquoted_value(input),
+    fn iter(&self) -> parser::Iter<'_> {
+        Box::new(
+            self.map
+                .iter()
+                .map(|(k, v)| (*k, StringOrStr::Str(v.as_ref()))),
+        )
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
     }
 }
 
+fn parse_one_key_value<'a>(
+    input: &'a str,
+    config: &ParserConfig,
+) -> NomResult<'a, (&'a str, Option<StringOrStr<'a>>)> {
+    // eat whitespace and comments
+    let input = grammar::skip_space(input, config);
+    if let Some((input, key)) = grammar::flag(input, config) {
+        let (input, _) = grammar::delimiter(input, config)?;
+        return Ok((input, (key, None)));
+    }
+    let (input, key) = grammar::key_and_separator(input, config)?;
+    let (input, value) = parse_value(input, config)?;
+    // eat the delimiter, and the whitespace and comments around it
+    let (input, _) = grammar::delimiter(input, config)?;
+
+    Ok((input, (key, Some(value))))
+}
+
+fn quoted_value(input: &str) -> NomResult<'_, &str> {
+    let start = input;
+    let (input, close) = grammar::open_quote(input)?;
+
+    // consume until we hit the closing quote
+    let (input, so_far) = expect(
+        start,
+        ParseError::UnterminatedQuote,
+        take_until(close)(input),
+    )?;
+    let (input, _quote) = tag(close)(input)?;
+
+    Ok((input, so_far))
+}
+
+/// Parses a value, without decoding escapes or stripping the indentation of a heredoc, so
+/// that it is a slice of the input unless it is a bare value whose lines had to be joined.
+fn parse_value<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, StringOrStr<'a>> {
+    let (input, value) = match grammar::value_kind(input, config)? {
+        (input, ValueKind::Quoted | ValueKind::TripleQuoted) => quoted_value(input)?,
+        (input, ValueKind::Literal) => grammar::literal_value(input)?,
+        (input, ValueKind::Heredoc) => {
+            let (input, (text, _)) = grammar::heredoc_value(input)?;
+            (input, text)
+        }
+        (input, ValueKind::Unquoted) => {
+            let (input, value) = grammar::unquoted_value(input, config)?;
+            if let Some(joined) = grammar::join_lines(value) {
+                return Ok((input, StringOrStr::String(joined)));
+            }
+            (input, value)
+        }
+    };
+    Ok((input, StringOrStr::Str(value)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,6 +376,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_single_quotes() {
+        const DATA: &str = "key='value with \"double\" quotes' empty='' apostrophe=it's";
+        let parser = Parser::new(DATA).unwrap();
+
+        let value = parser.get("key").unwrap();
+        assert_eq!(value, "value with \"double\" quotes");
+        // borrowed straight from the input
+        assert_eq!(value.as_ptr(), DATA[5..].as_ptr());
+        assert_eq!(parser.get("empty").unwrap(), "");
+        assert_eq!(parser.get("apostrophe").unwrap(), "it's");
+
+        assert!(matches!(
+            Parser::new("key='open").err().unwrap(),
+            ParseError::UnterminatedQuote(p) if p.offset == 4
+        ));
+    }
+
     #[test]
     fn test_no_data() {
         const DATA: &str = "   ";
@@ -173,4 +411,33 @@ mod tests {
             assert!(parser.is_err(), "Should have failed to parse: {:?}", data);
         }
     }
+
+    #[test]
+    fn test_error_kinds() {
+        fn err(data: &str) -> ParseError {
+            Parser::new(data).err().unwrap()
+        }
+
+        assert!(matches!(err("foo bar=1"), ParseError::MissingEquals(p) if p.offset == 4));
+        assert!(matches!(err(";foo=bar"), ParseError::InvalidKeyChar(p) if p.offset == 0));
+        assert!(matches!(err("a=1 =2"), ParseError::EmptyKey(p) if p.offset == 4));
+        assert!(
+            matches!(err("a=1\nq=\"foo"), ParseError::UnterminatedQuote(p) if p.line == 2 && p.column == 3)
+        );
+    }
+
+    #[test]
+    fn test_std_traits() {
+        let parser = Parser::new("one=1 two=\"2 2\"").unwrap();
+
+        assert_eq!(parser.clone(), parser);
+        assert_eq!(&parser["two"], "2 2");
+        assert_eq!(parser.keys().count(), 2);
+
+        let mut collected: Parser = parser.iter().collect();
+        assert_eq!(collected, parser);
+        collected.extend([("three", "3")]);
+        assert_eq!(collected.len(), 3);
+        assert!(parser.into_iter().any(|(k, v)| k == "two" && v == "2 2"));
+    }
 }
@@ -0,0 +1,587 @@
+//! Options that change how the input is parsed.
+//!
+//! ```
+//! use key_value_parser::config::{DuplicatePolicy, ParserConfig};
+//! use key_value_parser::full_copy::Parser;
+//!
+//! let config = ParserConfig::new().duplicate_policy(DuplicatePolicy::FirstWins);
+//! let parser = Parser::with_config("key=1 key=2", &config).unwrap();
+//! assert_eq!(parser.get("key").unwrap(), "1");
+//! ```
+//!
+//! The grammar can be changed to read other dialects of key value text:
+//!
+//! ```
+//! use key_value_parser::config::{Delimiter, ParserConfig};
+//! use key_value_parser::zero_parse;
+//!
+//! let config = ParserConfig::new()
+//!     .separator(':')
+//!     .delimiter(Delimiter::Char(','))
+//!     .space_around_separator(false);
+//! const DATA: &str = "host:localhost, port:8080";
+//! assert_eq!(zero_parse::parse_with_config(DATA, "port", &config).unwrap(), "8080");
+//! ```
+use std::borrow::Cow;
+
+use crate::grammar;
+
+/// A kind of comment.  A comment runs from its marker to the end of the line.  Comments may
+/// only appear between pairs, a marker inside a value is part of the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comment {
+    /// `# comment`, the only kind that is on by default
+    Hash,
+    /// `; comment`
+    Semicolon,
+    /// `// comment`
+    DoubleSlash,
+}
+impl Comment {
+    /// The marker that starts the comment
+    pub fn marker(self) -> &'static str {
+        match self {
+            Comment::Hash => "#",
+            Comment::Semicolon => ";",
+            Comment::DoubleSlash => "//",
+        }
+    }
+}
+
+/// What to do when a key appears more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DuplicatePolicy {
+    /// A repeated key is a [`ParseError::DuplicateKey`](crate::ParseError::DuplicateKey)
+    Error,
+    /// The first value is kept, later ones are ignored
+    FirstWins,
+    /// The last value is kept, earlier ones are replaced
+    #[default]
+    LastWins,
+    /// Every value is kept.  `get` returns the first one, `get_all` returns all of them in
+    /// the order they appear in the input.
+    Collect,
+}
+
+/// What separates one pair from the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Delimiter {
+    /// Any whitespace, as in `a=1 b=2`.  Bare values end at whitespace.
+    #[default]
+    Whitespace,
+    /// A line break, as in `a = 1\nb = two words`.  Bare values run to the end of the line
+    /// and may hold spaces, so only a comment on a line of its own is a comment.
+    Newline,
+    /// A character, as in `a=1,b=2` or `a=1&b=2`.  Bare values end at the character or at
+    /// whitespace, and whitespace may surround it.  A delimiter after the last pair is
+    /// allowed.
+    Char(char),
+}
+
+/// A kind of quotes.  A quote that is not allowed is taken as part of a bare value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quote {
+    /// `"value"`, where backslash escapes are decoded
+    Double,
+    /// `'value'`, which is taken literally
+    Single,
+}
+impl Quote {
+    /// The character that opens and closes the value
+    pub fn char(self) -> char {
+        match self {
+            Quote::Double => '"',
+            Quote::Single => '\'',
+        }
+    }
+}
+
+/// Options for the parsers.  The defaults match what [`Parser::new`](crate::full_copy::Parser::new)
+/// does.  The lists of comments, quotes and key characters may be slices or `Vec`s, so a dialect
+/// can be built at runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserConfig {
+    pub(crate) duplicate_policy: DuplicatePolicy,
+    pub(crate) strict_escapes: bool,
+    pub(crate) comments: Cow<'static, [Comment]>,
+    pub(crate) separator: char,
+    pub(crate) delimiter: Delimiter,
+    pub(crate) space_around_separator: bool,
+    pub(crate) quotes: Cow<'static, [Quote]>,
+    pub(crate) key_chars: Cow<'static, [char]>,
+    pub(crate) ascii_keys: bool,
+    pub(crate) min_key_len: usize,
+    pub(crate) max_key_len: Option<usize>,
+    pub(crate) flags: bool,
+    pub(crate) line_continuation: bool,
+    pub(crate) heredocs: bool,
+}
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            duplicate_policy: DuplicatePolicy::default(),
+            strict_escapes: false,
+            comments: Cow::Borrowed(&[Comment::Hash]),
+            separator: '=',
+            delimiter: Delimiter::default(),
+            space_around_separator: true,
+            quotes: Cow::Borrowed(&[Quote::Double, Quote::Single]),
+            key_chars: Cow::Borrowed(&['-', '_']),
+            ascii_keys: false,
+            min_key_len: 1,
+            max_key_len: None,
+            flags: false,
+            line_continuation: false,
+            heredocs: false,
+        }
+    }
+}
+impl ParserConfig {
+    /// The default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what happens to repeated keys.  Defaults to [`DuplicatePolicy::LastWins`].
+    pub fn duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Makes an unknown escape in a quoted value, such as `\q`, a
+    /// [`ParseError::InvalidEscape`](crate::ParseError::InvalidEscape) instead of the character
+    /// after the backslash.  Defaults to false.
+    pub fn strict_escapes(mut self, strict: bool) -> Self {
+        self.strict_escapes = strict;
+        self
+    }
+
+    /// Sets which kinds of comments are skipped between pairs.  Defaults to
+    /// [`Comment::Hash`] alone, an empty slice turns comments off.
+    ///
+    /// # Panics
+    ///
+    /// If a marker starts with the separator or the delimiter.
+    pub fn comments(mut self, comments: impl Into<Cow<'static, [Comment]>>) -> Self {
+        let comments = comments.into();
+        for comment in comments.iter() {
+            let marker = comment.marker();
+            assert!(
+                !marker.starts_with(|c| self.is_separator_or_delimiter(c)),
+                "`{marker}` cannot start a comment"
+            );
+        }
+        self.comments = comments;
+        self
+    }
+
+    /// Sets the character between a key and its value.  Defaults to `=`.
+    ///
+    /// # Panics
+    ///
+    /// If the character could be part of a key, is whitespace, or starts a comment or a quoted
+    /// value.
+    pub fn separator(mut self, separator: char) -> Self {
+        assert!(
+            !grammar::is_key_char(separator, &self)
+                && !separator.is_whitespace()
+                && !self.starts_comment_or_quote(separator),
+            "`{separator}` cannot separate a key from its value"
+        );
+        self.separator = separator;
+        self
+    }
+
+    /// Sets what separates one pair from the next.  Defaults to [`Delimiter::Whitespace`].
+    ///
+    /// # Panics
+    ///
+    /// If the delimiter is a character that could be part of a key, is whitespace, or starts a
+    /// comment or a quoted value.
+    pub fn delimiter(mut self, delimiter: Delimiter) -> Self {
+        if let Delimiter::Char(c) = delimiter {
+            assert!(
+                !grammar::is_key_char(c, &self)
+                    && !c.is_whitespace()
+                    && !self.starts_comment_or_quote(c),
+                "`{c}` cannot delimit pairs"
+            );
+        }
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets whether there may be whitespace on either side of the separator, as in
+    /// `key = value`.  Defaults to true.
+    pub fn space_around_separator(mut self, allowed: bool) -> Self {
+        self.space_around_separator = allowed;
+        self
+    }
+
+    /// Sets which quotes may surround a value.  Defaults to both [`Quote::Double`] and
+    /// [`Quote::Single`].
+    ///
+    /// # Panics
+    ///
+    /// If a quote is the separator or the delimiter.
+    pub fn quotes(mut self, quotes: impl Into<Cow<'static, [Quote]>>) -> Self {
+        let quotes = quotes.into();
+        for quote in quotes.iter() {
+            let c = quote.char();
+            assert!(
+                !self.is_separator_or_delimiter(c),
+                "`{c}` cannot quote values"
+            );
+        }
+        self.quotes = quotes;
+        self
+    }
+
+    /// Returns true if the character is the separator or the delimiter
+    fn is_separator_or_delimiter(&self, c: char) -> bool {
+        c == self.separator || self.delimiter == Delimiter::Char(c)
+    }
+
+    /// Returns true if the character starts one of the allowed comments or quoted values
+    fn starts_comment_or_quote(&self, c: char) -> bool {
+        self.comments
+            .iter()
+            .any(|comment| comment.marker().starts_with(c))
+            || self.quotes.iter().any(|quote| quote.char() == c)
+    }
+
+    /// Sets the characters that may appear in keys besides letters and digits.  Defaults to
+    /// `-` and `_`.  `&['-', '_', '.', '/', ':']` allows keys such as `http.status`, `k8s/app`
+    /// or `user:id`, as long as `:` is not also the separator.
+    ///
+    /// # Panics
+    ///
+    /// If one of the characters is whitespace, the separator, the delimiter or a quote.
+    pub fn key_chars(mut self, chars: impl Into<Cow<'static, [char]>>) -> Self {
+        let chars = chars.into();
+        for &c in chars.iter() {
+            assert!(
+                !c.is_whitespace() && !self.is_separator_or_delimiter(c) && c != '"' && c != '\'',
+                "`{c}` cannot be part of a key"
+            );
+        }
+        self.key_chars = chars;
+        self
+    }
+
+    /// Sets whether the letters and digits in keys have to be ASCII.  Defaults to false, which
+    /// allows any Unicode letter or digit.
+    pub fn ascii_keys(mut self, ascii: bool) -> Self {
+        self.ascii_keys = ascii;
+        self
+    }
+
+    /// Sets the fewest characters a key may have.  Defaults to 1, 0 allows empty keys as in
+    /// `=value`.  A key that is too short is a
+    /// [`ParseError::KeyTooShort`](crate::ParseError::KeyTooShort), or a
+    /// [`ParseError::EmptyKey`](crate::ParseError::EmptyKey) if it is empty.
+    pub fn min_key_len(mut self, len: usize) -> Self {
+        self.min_key_len = len;
+        self
+    }
+
+    /// Sets the most characters a key may have.  There is no limit by default.  A key that is
+    /// too long is a [`ParseError::KeyTooLong`](crate::ParseError::KeyTooLong).
+    pub fn max_key_len(mut self, len: usize) -> Self {
+        self.max_key_len = Some(len);
+        self
+    }
+
+    /// Sets whether a key may stand on its own, without a separator or a value, as in
+    /// `quiet ro debug=1`.  Such a key is a flag, see
+    /// [`KeyValueParser::is_flag`](crate::KeyValueParser::is_flag).  Defaults to false, which
+    /// makes a bare key a [`ParseError::MissingEquals`](crate::ParseError::MissingEquals).
+    ///
+    /// Flags are kept apart from the pairs: they are not counted by `len` nor returned by
+    /// `iter`, and the duplicate policy does not apply to them.
+    pub fn flags(mut self, allowed: bool) -> Self {
+        self.flags = allowed;
+        self
+    }
+
+    /// Sets whether a backslash at the end of a line joins the next line to it, as in Java
+    /// properties and shell environment files.  Defaults to false.
+    ///
+    /// Inside a bare value the backslash, the line break and the spaces that start the next
+    /// line are dropped, so `one\` at the end of a line followed by `  two` reads as `onetwo`.
+    /// Between pairs, and around the separator, they count as a space.  Quoted values and
+    /// heredocs are not affected.
+    ///
+    /// [`zero_copy`](crate::zero_copy) only hands out slices of the input, so it cannot join
+    /// the lines of a bare value and returns
+    /// [`ParseError::ContinuedValue`](crate::error::ParseError::ContinuedValue) instead.
+    ///
+    /// ```
+    /// use key_value_parser::config::{Delimiter, ParserConfig};
+    /// use key_value_parser::full_almost_zero_copy::Parser;
+    ///
+    /// let config = ParserConfig::new()
+    ///     .delimiter(Delimiter::Newline)
+    ///     .line_continuation(true);
+    /// const DATA: &str = "fruits = apple, \\\n         banana\nempty = \\\n";
+    /// let parser = Parser::with_config(DATA, &config).unwrap();
+    /// assert_eq!(parser.get("fruits").unwrap(), "apple, banana");
+    /// assert_eq!(parser.get("empty").unwrap(), "");
+    /// ```
+    pub fn line_continuation(mut self, allowed: bool) -> Self {
+        self.line_continuation = allowed;
+        self
+    }
+
+    /// Sets whether a value may be a heredoc: `<<TAG` at the end of a line, then every line up
+    /// to one that holds only `TAG`.  Defaults to false, which reads `<<TAG` as a bare value.
+    ///
+    /// The text is taken literally.  With `<<~TAG` the indentation that all its lines share is
+    /// stripped, except by [`zero_copy`](crate::zero_copy) and
+    /// [`almost_zero_copy`](crate::almost_zero_copy): they only hand out slices of the input, so
+    /// they keep the text as written.
+    ///
+    /// ```
+    /// use key_value_parser::config::ParserConfig;
+    /// use key_value_parser::full_copy::Parser;
+    ///
+    /// const DATA: &str = "sql=<<~SQL\n  SELECT *\n    FROM t\n  SQL\nnext=1";
+    /// let parser = Parser::with_config(DATA, &ParserConfig::new().heredocs(true)).unwrap();
+    /// assert_eq!(parser.get("sql"), Some("SELECT *\n  FROM t"));
+    /// assert_eq!(parser.get("next"), Some("1"));
+    ///
+    /// let parser = Parser::new("a=<<x b=1").unwrap();
+    /// assert_eq!(parser.get("a"), Some("<<x"));
+    /// ```
+    pub fn heredocs(mut self, allowed: bool) -> Self {
+        self.heredocs = allowed;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::every_strategy;
+    use crate::{almost_zero_copy, full_almost_zero_copy, full_copy, zero_copy, zero_parse};
+    use crate::{KeyValueParser, ParseError, ValueError};
+
+    const DATA: &str = "key=1 other=x key=2 key=\"3\"";
+
+    fn config(policy: DuplicatePolicy) -> ParserConfig {
+        ParserConfig::new().duplicate_policy(policy)
+    }
+
+    /// What every strategy returns for `key` under a policy
+    fn lookups(policy: DuplicatePolicy) -> [String; 5] {
+        let config = config(policy);
+        [
+            full_copy::Parser::with_config(DATA, &config)
+                .unwrap()
+                .get("key")
+                .unwrap()
+                .to_string(),
+            full_almost_zero_copy::Parser::with_config(DATA, &config)
+                .unwrap()
+                .get("key")
+                .unwrap()
+                .to_string(),
+            zero_copy::Parser::with_config(DATA, &config)
+                .unwrap()
+                .get("key")
+                .unwrap()
+                .to_string(),
+            almost_zero_copy::Parser::with_config(DATA, &config)
+                .unwrap()
+                .get("key")
+                .unwrap()
+                .to_string(),
+            zero_parse::parse_with_config(DATA, "key", &config)
+                .unwrap()
+                .as_ref()
+                .to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_strategies_agree_on_duplicates() {
+        assert_eq!(lookups(DuplicatePolicy::FirstWins), ["1"; 5]);
+        assert_eq!(lookups(DuplicatePolicy::LastWins), ["3"; 5]);
+        assert_eq!(lookups(DuplicatePolicy::Collect), ["1"; 5]);
+
+        // the default is the same everywhere too
+        assert_eq!(full_copy::Parser::new(DATA).unwrap().get("key"), Some("3"));
+        assert_eq!(zero_parse::parse(DATA, "key").unwrap().as_ref(), "3");
+    }
+
+    #[test]
+    fn test_collect() {
+        let config = config(DuplicatePolicy::Collect);
+
+        let parser = full_copy::Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.len(), 2);
+        assert_eq!(parser.get_all("key").collect::<Vec<_>>(), ["1", "2", "3"]);
+        assert_eq!(parser.get_all("other").collect::<Vec<_>>(), ["x"]);
+        assert_eq!(parser.get_all("missing").count(), 0);
+
+        let parser = full_almost_zero_copy::Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.get_all("key").collect::<Vec<_>>(), ["1", "2", "3"]);
+
+        let parser = zero_copy::Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.get_all("key").collect::<Vec<_>>(), ["1", "2", "3"]);
+
+        let parser = almost_zero_copy::Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.get_all("key").collect::<Vec<_>>(), ["1", "2", "3"]);
+
+        let parser = zero_parse::Parser::with_config(DATA, &config).unwrap();
+        let values = parser.get_all("key").collect::<Vec<_>>();
+        assert_eq!(
+            values.iter().map(AsRef::as_ref).collect::<Vec<_>>(),
+            ["1", "2", "3"]
+        );
+    }
+
+    #[test]
+    fn test_lists_built_at_runtime() {
+        /// Parses with a strategy, and returns what it finds for the keys
+        fn values<'a, P: KeyValueParser<'a>>(input: &'a str, config: &ParserConfig) -> [String; 2] {
+            let parser = P::with_config(input, config).unwrap();
+            ["a.b", "c:d"].map(|key| parser.get(key).unwrap().to_string())
+        }
+
+        // a dialect read from elsewhere at runtime, rather than written out in the source
+        let (comments, key_chars) = ("//", ".:");
+        let config = ParserConfig::new()
+            .comments(
+                [Comment::Hash, Comment::Semicolon, Comment::DoubleSlash]
+                    .into_iter()
+                    .filter(|comment| comment.marker() == comments)
+                    .collect::<Vec<_>>(),
+            )
+            .quotes(vec![Quote::Single])
+            .key_chars(key_chars.chars().collect::<Vec<_>>());
+        const INPUT: &str = "a.b='x y' // note\nc:d=\"z\"";
+        for values in every_strategy!(values(INPUT, &config)) {
+            assert_eq!(values, ["x y", "\"z\""]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be part of a key")]
+    fn test_key_chars_cannot_hold_the_separator() {
+        let _ = ParserConfig::new().separator(':').key_chars(&[':']);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot separate a key")]
+    fn test_separator_cannot_be_a_key_char() {
+        let _ = ParserConfig::new().separator('a');
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot delimit pairs")]
+    fn test_delimiter_cannot_start_a_comment() {
+        let _ = ParserConfig::new().delimiter(Delimiter::Char('#'));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot separate a key")]
+    fn test_separator_cannot_be_a_quote() {
+        let _ = ParserConfig::new().separator('\'');
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot start a comment")]
+    fn test_comment_cannot_start_with_the_delimiter() {
+        let _ = ParserConfig::new()
+            .delimiter(Delimiter::Char('/'))
+            .comments(&[Comment::DoubleSlash]);
+    }
+
+    #[test]
+    fn test_markers_and_quotes_that_are_off() {
+        let config = ParserConfig::new()
+            .comments(&[])
+            .quotes(&[Quote::Double])
+            .delimiter(Delimiter::Char('#'))
+            .separator('\'');
+        let parser = full_copy::Parser::with_config("a'1#b'\"2\"", &config).unwrap();
+        assert_eq!(parser.get("a"), Some("1"));
+        assert_eq!(parser.get("b"), Some("2"));
+    }
+
+    #[test]
+    fn test_strict_escapes() {
+        const DATA: &str = "ok=\"a\\tb\" odd=\"\\q\"";
+        let strict = ParserConfig::new().strict_escapes(true);
+        let is_invalid =
+            |e: ParseError| matches!(e, ParseError::InvalidEscape(p) if p.offset == 15);
+
+        assert_eq!(full_copy::Parser::new(DATA).unwrap().get("odd"), Some("q"));
+        assert_eq!(zero_parse::parse(DATA, "odd").unwrap().as_ref(), "q");
+
+        assert!(is_invalid(
+            full_copy::Parser::with_config(DATA, &strict).err().unwrap()
+        ));
+        assert!(is_invalid(
+            full_almost_zero_copy::Parser::with_config(DATA, &strict)
+                .err()
+                .unwrap()
+        ));
+        assert!(is_invalid(
+            zero_parse::Parser::with_config(DATA, &strict)
+                .err()
+                .unwrap()
+        ));
+        // a lookup only needs the input up to its key
+        let ok = zero_parse::parse_with_config(DATA, "ok", &strict).unwrap();
+        assert_eq!(ok.as_ref(), "a\tb");
+        let Err(ValueError::Parse { error, .. }) =
+            zero_parse::parse_with_config(DATA, "odd", &strict)
+        else {
+            panic!("expected a parse error");
+        };
+        assert!(is_invalid(error));
+    }
+
+    #[test]
+    fn test_error() {
+        let config = config(DuplicatePolicy::Error);
+        let is_duplicate =
+            |e: ParseError| matches!(e, ParseError::DuplicateKey(p) if p.offset == 14);
+
+        assert!(is_duplicate(
+            full_copy::Parser::with_config(DATA, &config).err().unwrap()
+        ));
+        assert!(is_duplicate(
+            full_almost_zero_copy::Parser::with_config(DATA, &config)
+                .err()
+                .unwrap()
+        ));
+        assert!(is_duplicate(
+            zero_copy::Parser::with_config(DATA, &config).err().unwrap()
+        ));
+        assert!(is_duplicate(
+            almost_zero_copy::Parser::with_config(DATA, &config)
+                .err()
+                .unwrap()
+        ));
+        assert!(is_duplicate(
+            zero_parse::Parser::with_config(DATA, &config)
+                .err()
+                .unwrap()
+        ));
+        // looking up any key finds the duplicate, not just looking up the repeated one
+        let Err(ValueError::Parse { error, .. }) =
+            zero_parse::parse_with_config(DATA, "other", &config)
+        else {
+            panic!("expected a parse error");
+        };
+        assert!(is_duplicate(error));
+
+        // lenient parsing keeps the first value and reports the rest
+        let (parser, errors) = full_copy::Parser::with_config_lenient(DATA, &config);
+        assert_eq!(parser.get("key"), Some("1"));
+        assert_eq!(errors.len(), 2);
+    }
+}
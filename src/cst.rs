@@ -0,0 +1,693 @@
+//! cst keeps every byte of the input, so that it can be edited and written back out without
+//! disturbing anything that was not changed.
+//!
+//! The other strategies only keep the pairs.  A [`Document`] also keeps the whitespace and
+//! comments between them, the spacing around each separator, and each value exactly as it
+//! was written, with its quotes and escapes.  Printing a document that was not edited gives back
+//! the input, byte for byte.
+//!
+//! ```
+//! use key_value_parser::cst::Document;
+//!
+//! const DATA: &str = "host = localhost\n  port=80   name=\"web\"\n";
+//! let mut document = Document::parse(DATA).unwrap();
+//! assert_eq!(document.to_string(), DATA);
+//!
+//! document.set("port", "8080").unwrap();
+//! document.set("name", "web 1").unwrap();
+//! assert_eq!(
+//!     document.to_string(),
+//!     "host = localhost\n  port=8080   name=\"web 1\"\n"
+//! );
+//! ```
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::config::{Delimiter, ParserConfig, Quote};
+use crate::error::{to_parse_error, NomResult, ParseError};
+use crate::full_almost_zero_copy::{parse_value_with_config, StringOrStr};
+use crate::grammar::{self, ValueKind};
+use crate::writer;
+use crate::zero_parse;
+
+/// Why a document could not be edited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A value that has to be quoted to read back, in a dialect that does not allow the quotes
+    /// it needs
+    Unquotable(String),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unquotable(value) => {
+                write!(f, "`{value}` cannot be written without the quotes it needs")
+            }
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// A piece of the input: a run of whitespace, a comment, a delimiter, or a pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node<'a> {
+    /// A run of whitespace, or a line continuation under
+    /// [`ParserConfig::line_continuation`]
+    Whitespace(Cow<'a, str>),
+    /// A comment, from its marker up to the end of its line
+    Comment(Cow<'a, str>),
+    /// The character between two pairs, under [`Delimiter::Char`]
+    Delimiter(Cow<'a, str>),
+    /// A pair, or a flag under [`ParserConfig::flags`]
+    Pair(Pair<'a>),
+}
+impl fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Whitespace(s) | Node::Comment(s) | Node::Delimiter(s) => f.write_str(s),
+            Node::Pair(pair) => pair.fmt(f),
+        }
+    }
+}
+
+/// A key value pair, with the spacing around its separator and its value as they were
+/// written.  A flag is a key on its own, with no separator and an empty value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pair<'a> {
+    key: Cow<'a, str>,
+    /// None for a flag
+    separator: Option<char>,
+    before_separator: Cow<'a, str>,
+    after_separator: Cow<'a, str>,
+    raw_value: Cow<'a, str>,
+    kind: ValueKind,
+    /// The dialect the value is read and written in
+    config: ParserConfig,
+}
+impl<'a> Pair<'a> {
+    /// A new pair, with no spacing around the `=`.  The value is quoted if it has to be.
+    pub fn new(key: impl Into<Cow<'a, str>>, value: &str) -> Self {
+        Self::with_config(key, value, &ParserConfig::default())
+            .expect("double quotes are allowed by default")
+    }
+
+    /// A new pair in the dialect of the configuration, quoting the value if it holds the
+    /// delimiter, see [`Pair::new`].  Fails if the value needs quotes the dialect does not
+    /// allow.
+    fn with_config(
+        key: impl Into<Cow<'a, str>>,
+        value: &str,
+        config: &ParserConfig,
+    ) -> Result<Self, Error> {
+        let (raw_value, kind) = encode(value, false, config)?;
+        Ok(Self {
+            key: key.into(),
+            separator: Some(config.separator),
+            before_separator: Cow::Borrowed(""),
+            after_separator: Cow::Borrowed(""),
+            raw_value: Cow::Owned(raw_value),
+            kind,
+            config: config.clone(),
+        })
+    }
+
+    /// A new flag, a key without a separator or a value
+    pub fn flag(key: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            key: key.into(),
+            separator: None,
+            before_separator: Cow::Borrowed(""),
+            after_separator: Cow::Borrowed(""),
+            raw_value: Cow::Borrowed(""),
+            kind: ValueKind::Unquoted,
+            config: ParserConfig::default(),
+        }
+    }
+
+    /// The key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns true if the key stands on its own, without a separator or a value
+    pub fn is_flag(&self) -> bool {
+        self.separator.is_none()
+    }
+
+    /// The value, with its quotes removed and its escapes decoded as the dialect of its
+    /// document says.  A pair that was made in another dialect and pushed into the document may
+    /// not be valid in it, in which case an error is returned.
+    pub fn value(&self) -> Result<StringOrStr<'_>, ParseError> {
+        let (_, value) = parse_value_with_config(&self.raw_value, &self.config)
+            .map_err(|e| to_parse_error(&self.raw_value, e))?;
+        Ok(value)
+    }
+
+    /// The value as it is written, with its quotes and escapes
+    pub fn raw_value(&self) -> &str {
+        &self.raw_value
+    }
+
+    /// Returns true if the value is written in quotes, double, triple or single, or as a
+    /// heredoc
+    pub fn is_quoted(&self) -> bool {
+        self.kind != ValueKind::Unquoted
+    }
+
+    /// Replaces the value, keeping the spacing around the separator.  A quoted value stays
+    /// quoted, an unquoted one is only quoted if the new value needs it or holds the
+    /// delimiter.  A value in single quotes stays in single quotes unless the new value has a
+    /// single quote in it.  A flag becomes a pair.
+    ///
+    /// If the value would have to be quoted, and the quotes it needs are not allowed in the
+    /// dialect, an error is returned and the pair is left as it was.
+    pub fn set_value(&mut self, value: &str) -> Result<(), Error> {
+        let (raw, kind) = if self.kind == ValueKind::Literal && !value.contains('\'') {
+            (format!("'{value}'"), ValueKind::Literal)
+        } else {
+            encode(value, self.is_quoted(), &self.config)?
+        };
+        self.separator.get_or_insert(self.config.separator);
+        self.raw_value = Cow::Owned(raw);
+        self.kind = kind;
+        Ok(())
+    }
+}
+impl fmt::Display for Pair<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.key)?;
+        if let Some(separator) = self.separator {
+            write!(
+                f,
+                "{}{}{}{}",
+                self.before_separator, separator, self.after_separator, self.raw_value
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a value the way it has to appear in the input.  It is quoted if `quoted` is set, or
+/// if it would not parse back otherwise.  Without double quotes in the dialect, single quotes
+/// or a bare value are used if they read back as the value.
+fn encode(value: &str, quoted: bool, config: &ParserConfig) -> Result<(String, ValueKind), Error> {
+    let holds_delimiter = matches!(config.delimiter, Delimiter::Char(d) if value.contains(d));
+    if !quoted && !holds_delimiter && !writer::needs_quotes(value) {
+        return Ok((value.to_string(), ValueKind::Unquoted));
+    }
+
+    if config.quotes.contains(&Quote::Double) {
+        let mut raw = String::with_capacity(value.len() + 2);
+        writer::write_quoted(&mut raw, value).expect("writing to a String cannot fail");
+        return Ok((raw, ValueKind::Quoted));
+    }
+    [
+        (format!("'{value}'"), ValueKind::Literal),
+        (value.to_string(), ValueKind::Unquoted),
+    ]
+    .into_iter()
+    .find(|(raw, _)| reads_back(raw, value, config))
+    .ok_or_else(|| Error::Unquotable(value.to_string()))
+}
+
+/// Returns true if `raw` is read as exactly `value`, with nothing left over
+fn reads_back(raw: &str, value: &str, config: &ParserConfig) -> bool {
+    matches!(parse_value_with_config(raw, config), Ok(("", read)) if read == value)
+}
+
+/// Every token of the input, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Document<'a> {
+    nodes: Vec<Node<'a>>,
+    config: ParserConfig,
+}
+impl<'a> Document<'a> {
+    /// Parses the input.  If the input cannot be parsed, an error will be returned.
+    pub fn parse(input: &'a str) -> Result<Self, ParseError> {
+        Self::parse_with_config(input, &ParserConfig::default())
+    }
+
+    /// Parses the input with the given configuration.  Only the options that change what
+    /// parses apply, a document keeps every pair whatever the duplicate policy.  Pairs that
+    /// are added or edited later are written in the same dialect.
+    pub fn parse_with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        let mut nodes = Vec::new();
+
+        let mut head = grammar::skip_space(input, config);
+        push_between(&mut nodes, &input[..input.len() - head.len()], config);
+        while !head.is_empty() {
+            let (rest, pair) = parse_pair(head, config).map_err(|e| to_parse_error(input, e))?;
+            nodes.push(Node::Pair(pair));
+
+            let (next, _) =
+                grammar::delimiter(rest, config).map_err(|e| to_parse_error(input, e))?;
+            push_between(&mut nodes, &rest[..rest.len() - next.len()], config);
+            head = next;
+        }
+
+        Ok(Self {
+            nodes,
+            config: config.clone(),
+        })
+    }
+
+    /// Every node of the document, in order
+    pub fn nodes(&self) -> &[Node<'a>] {
+        &self.nodes
+    }
+
+    /// Iterates over the pairs, in the order they appear.  Flags are left out.
+    pub fn pairs(&self) -> impl Iterator<Item = &Pair<'a>> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::Pair(pair) if !pair.is_flag() => Some(pair),
+            Node::Pair(_) | Node::Whitespace(_) | Node::Comment(_) | Node::Delimiter(_) => None,
+        })
+    }
+
+    /// Iterates over the keys that appear on their own, under [`ParserConfig::flags`], in the
+    /// order they appear
+    pub fn flags(&self) -> impl Iterator<Item = &str> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::Pair(pair) if pair.is_flag() => Some(pair.key()),
+            Node::Pair(_) | Node::Whitespace(_) | Node::Comment(_) | Node::Delimiter(_) => None,
+        })
+    }
+
+    /// Returns true if the key appears on its own, without a value
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.flags().any(|k| k == key)
+    }
+
+    /// Gets a value.  A repeated key gives its last value, like the other strategies do.
+    pub fn get(&self, key: &str) -> Option<StringOrStr<'_>> {
+        let pair = self.pairs().filter(|p| p.key() == key).last()?;
+        // values are checked when they are parsed or set, so only a pushed pair can fail
+        pair.value().ok()
+    }
+
+    /// Sets the value of a key.  The last pair with the key is changed in place, keeping its
+    /// spacing and quoting, or the last flag if it comes after it.  If there is no such pair
+    /// or flag, a pair is added at the end.
+    ///
+    /// Fails, leaving the document as it was, if the value needs quotes that the dialect
+    /// does not allow.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let last = self
+            .nodes
+            .iter()
+            .rposition(|node| matches!(node, Node::Pair(pair) if pair.key() == key));
+        match last.map(|i| &mut self.nodes[i]) {
+            Some(Node::Pair(pair)) => pair.set_value(value),
+            _ => {
+                let pair = Pair::with_config(key.to_string(), value, &self.config)?;
+                self.push(pair);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds a pair or a flag at the end, written with the document's separator.  It is
+    /// separated from the one before it by the delimiter: a space, a newline, or the delimiter
+    /// character followed by a space.  A newline is used instead of a space if the document
+    /// ends in a comment.  Trailing whitespace stays at the end of the document.  From then on
+    /// the pair's value is read in the document's dialect.
+    pub fn push(&mut self, mut pair: Pair<'a>) {
+        if !pair.is_flag() {
+            pair.separator = Some(self.config.separator);
+        }
+        pair.config = self.config.clone();
+
+        let trailing = match self.nodes.last() {
+            Some(Node::Whitespace(_)) => self.nodes.pop(),
+            _ => None,
+        };
+        // the pair before has to be followed by a delimiter, unless it already is
+        let last_pair_or_delimiter = self
+            .nodes
+            .iter()
+            .rev()
+            .find(|node| matches!(node, Node::Pair(_) | Node::Delimiter(_)));
+        let delimiter = match self.config.delimiter {
+            Delimiter::Char(d) if matches!(last_pair_or_delimiter, Some(Node::Pair(_))) => Some(d),
+            _ => None,
+        };
+
+        if let Some(Node::Comment(_)) = self.nodes.last() {
+            self.nodes.push(Node::Whitespace(Cow::Borrowed("\n")));
+        }
+        if let Some(d) = delimiter {
+            self.nodes.push(Node::Delimiter(Cow::Owned(d.to_string())));
+        }
+        match self.nodes.last() {
+            None | Some(Node::Whitespace(_)) => {}
+            Some(_) if self.config.delimiter == Delimiter::Newline => {
+                self.nodes.push(Node::Whitespace(Cow::Borrowed("\n")))
+            }
+            Some(_) => self.nodes.push(Node::Whitespace(Cow::Borrowed(" "))),
+        }
+        self.nodes.push(Node::Pair(pair));
+        self.nodes.extend(trailing);
+    }
+
+    /// Removes every pair and flag with the key, along with the whitespace and delimiter that
+    /// separated it from the pair before it, or from the pair after it if it came first.
+    /// Returns true if anything was removed.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let before = self.nodes.len();
+
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        let mut after_pair = false;
+        // whitespace that followed a removed first pair, dropped if another pair follows it
+        let mut skipped = None;
+        for node in self.nodes.drain(..) {
+            match node {
+                Node::Pair(pair) if pair.key() == key => {
+                    if after_pair {
+                        while let Some(Node::Whitespace(_) | Node::Delimiter(_)) = nodes.last() {
+                            nodes.pop();
+                        }
+                    } else {
+                        skipped = Some(None);
+                    }
+                }
+                Node::Whitespace(_) if skipped.is_some() => skipped = Some(Some(node)),
+                Node::Delimiter(_) if skipped.is_some() => skipped = Some(None),
+                node => {
+                    skipped = None;
+                    after_pair |= matches!(node, Node::Pair(_));
+                    nodes.push(node);
+                }
+            }
+        }
+        nodes.extend(skipped.flatten());
+        self.nodes = nodes;
+
+        self.nodes.len() != before
+    }
+
+    /// Returns how many pairs there are, counting repeated keys once per pair.  Flags are not
+    /// counted.
+    pub fn len(&self) -> usize {
+        self.pairs().count()
+    }
+
+    /// Returns true if there are no pairs
+    pub fn is_empty(&self) -> bool {
+        self.pairs().next().is_none()
+    }
+}
+impl fmt::Display for Document<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.nodes.iter().try_for_each(|node| node.fmt(f))
+    }
+}
+
+/// Parses one pair or flag, keeping each piece as it was written.
+fn parse_pair<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, Pair<'a>> {
+    if let Some((input, key)) = grammar::flag(input, config) {
+        let flag = Pair {
+            config: config.clone(),
+            ..Pair::flag(key)
+        };
+        return Ok((input, flag));
+    }
+    let (input, key) = grammar::key(input, config)?;
+    let rest = grammar::separator_space(input, config);
+    let before_separator = &input[..input.len() - rest.len()];
+    let (input, _) = grammar::separator(rest, config)?;
+    let value = grammar::value_start(input, config);
+    let after_separator = &input[..input.len() - value.len()];
+    let (_, kind) = grammar::value_kind(value, config)?;
+    let (input, _) = zero_parse::eat_value(value, config)?;
+    let raw_value = &value[..value.len() - input.len()];
+
+    let pair = Pair {
+        key: Cow::Borrowed(key),
+        separator: Some(config.separator),
+        before_separator: Cow::Borrowed(before_separator),
+        after_separator: Cow::Borrowed(after_separator),
+        raw_value: Cow::Borrowed(raw_value),
+        kind,
+        config: config.clone(),
+    };
+    Ok((input, pair))
+}
+
+/// Splits what lies between two pairs into whitespace, comments and delimiters
+fn push_between<'a>(nodes: &mut Vec<Node<'a>>, mut between: &'a str, config: &ParserConfig) {
+    while !between.is_empty() {
+        let rest = between.trim_start();
+        let (node, len) = if rest.len() < between.len() {
+            let whitespace = &between[..between.len() - rest.len()];
+            (
+                Node::Whitespace(Cow::Borrowed(whitespace)),
+                whitespace.len(),
+            )
+        } else if let Some(comment) = grammar::comment(between, config) {
+            (Node::Comment(Cow::Borrowed(comment)), comment.len())
+        } else if let Some(rest) = grammar::continuation(between, config) {
+            let len = between.len() - rest.len();
+            (Node::Whitespace(Cow::Borrowed(&between[..len])), len)
+        } else {
+            // grammar::delimiter only lets through the delimiter character
+            let len = between.chars().next().map_or(0, char::len_utf8);
+            (Node::Delimiter(Cow::Borrowed(&between[..len])), len)
+        };
+        nodes.push(node);
+        between = &between[len..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Comment, Quote};
+
+    #[test]
+    fn test_round_trip() {
+        for data in [
+            "",
+            "   ",
+            "a=1",
+            "\n\ta = 1\r\n  b =\t\"two \\\" 2\"  c=3\n\n",
+            "x=\"1\"y=2",
+            "a='\\n \"' b=2",
+            "# header\r\na=1 # trailing\n\n# footer",
+            "#",
+            "a=#not-a-comment",
+        ] {
+            assert_eq!(Document::parse(data).unwrap().to_string(), data);
+        }
+
+        let config = ParserConfig::new().heredocs(true);
+        const HEREDOC: &str = "sql=<<~SQL\n  SELECT 1\n  SQL\nb=\"\"\"\n\"x\"\n\"\"\" # note";
+        let document = Document::parse_with_config(HEREDOC, &config).unwrap();
+        assert_eq!(document.to_string(), HEREDOC);
+    }
+
+    #[test]
+    fn test_get() {
+        let document = Document::parse("a=1 b=\"x \\\"y\\\"\" a=2").unwrap();
+        assert_eq!(document.len(), 3);
+        assert_eq!(document.get("a").unwrap(), "2");
+        assert_eq!(document.get("b").unwrap(), "x \"y\"");
+        assert!(document.get("c").is_none());
+
+        let b = document.pairs().nth(1).unwrap();
+        assert!(b.is_quoted());
+        assert_eq!(b.raw_value(), "\"x \\\"y\\\"\"");
+
+        const DATA: &str = "sql=<<~SQL\n  SELECT 1\n  SQL\nb=\"\"\"say \"x\"!\"\"\"";
+        let config = ParserConfig::new().heredocs(true);
+        let mut document = Document::parse_with_config(DATA, &config).unwrap();
+        assert_eq!(document.get("sql").unwrap(), "SELECT 1");
+        assert_eq!(document.get("b").unwrap(), "say \"x\"!");
+        document.set("sql", "SELECT 2\n").unwrap();
+        assert_eq!(
+            document.to_string(),
+            "sql=\"SELECT 2\\n\"\nb=\"\"\"say \"x\"!\"\"\""
+        );
+    }
+
+    #[test]
+    fn test_set_keeps_quoting() {
+        let mut document = Document::parse("a = 1\tb=\"2\" a=3").unwrap();
+        document.set("a", "with space").unwrap();
+        document.set("b", "2").unwrap();
+        assert_eq!(document.to_string(), "a = 1\tb=\"2\" a=\"with space\"");
+
+        document.set("a", "x\\y").unwrap();
+        assert_eq!(document.get("a").unwrap(), "x\\y");
+        assert_eq!(document.to_string(), "a = 1\tb=\"2\" a=\"x\\\\y\"");
+
+        let mut literal = Document::parse("path='C:\\temp'").unwrap();
+        assert_eq!(literal.get("path").unwrap(), "C:\\temp");
+        literal.set("path", "D:\\\"x\"").unwrap();
+        assert_eq!(literal.to_string(), "path='D:\\\"x\"'");
+        literal.set("path", "it's").unwrap();
+        assert_eq!(literal.to_string(), "path=\"it's\"");
+    }
+
+    #[test]
+    fn test_push_and_remove() {
+        let mut document = Document::parse("a=1  b=2\n").unwrap();
+        document.set("c", "").unwrap();
+        assert_eq!(document.to_string(), "a=1  b=2 c=\"\"\n");
+
+        assert!(document.remove("b"));
+        assert_eq!(document.to_string(), "a=1 c=\"\"\n");
+        assert!(document.remove("a"));
+        assert_eq!(document.to_string(), "c=\"\"\n");
+        assert!(!document.remove("a"));
+
+        let mut document = Document::parse("  a=1 a=2\tb=3 a=4\n").unwrap();
+        assert!(document.remove("a"));
+        assert_eq!(document.to_string(), "  b=3\n");
+        assert!(document.remove("b"));
+        assert_eq!(document.to_string(), "  \n");
+    }
+
+    #[test]
+    fn test_comments() {
+        let mut document = Document::parse("# settings\na=1 # the first\n").unwrap();
+        assert_eq!(document.len(), 1);
+        assert!(matches!(&document.nodes()[2], Node::Pair(_)));
+        assert!(matches!(&document.nodes()[4], Node::Comment(c) if c == "# the first"));
+
+        document.set("b", "2").unwrap();
+        assert_eq!(document.to_string(), "# settings\na=1 # the first\nb=2\n");
+        assert_eq!(Document::parse(&document.to_string()).unwrap().len(), 2);
+
+        let config = ParserConfig::new().comments(&[Comment::DoubleSlash]);
+        let document = Document::parse_with_config("// note\na=1", &config).unwrap();
+        assert_eq!(document.to_string(), "// note\na=1");
+        assert!(Document::parse_with_config("# note\na=1", &config).is_err());
+    }
+
+    #[test]
+    fn test_dialects() {
+        let config = ParserConfig::new()
+            .separator(':')
+            .delimiter(Delimiter::Char(','));
+        const DATA: &str = "a : 1, b:'x' ,c:\"y\" # end\n";
+        let mut document = Document::parse_with_config(DATA, &config).unwrap();
+        assert_eq!(document.to_string(), DATA);
+        assert_eq!(document.len(), 3);
+
+        document.set("b", "1,2").unwrap();
+        document.set("d", "3,4").unwrap();
+        assert_eq!(
+            document.to_string(),
+            "a : 1, b:'1,2' ,c:\"y\" # end\n, d:\"3,4\"\n"
+        );
+        assert_eq!(document.get("d").unwrap(), "3,4");
+
+        assert!(document.remove("b"));
+        assert!(document.remove("a"));
+        assert_eq!(document.to_string(), "c:\"y\" # end\n, d:\"3,4\"\n");
+        let text = document.to_string();
+        assert_eq!(
+            Document::parse_with_config(&text, &config).unwrap().len(),
+            2
+        );
+
+        let config = ParserConfig::new()
+            .delimiter(Delimiter::Newline)
+            .quotes(&[]);
+        let mut document = Document::parse_with_config("title = Hello \"you\"\n", &config).unwrap();
+        assert_eq!(document.get("title").unwrap(), "Hello \"you\"");
+        assert!(!document.pairs().next().unwrap().is_quoted());
+        document.set("next", "x").unwrap();
+        assert_eq!(document.to_string(), "title = Hello \"you\"\nnext=x\n");
+        // a value with a line break cannot be written without quotes
+        assert_eq!(
+            document.set("next", "a\nb"),
+            Err(Error::Unquotable("a\nb".to_string()))
+        );
+        assert_eq!(
+            document.set("n", "a\nb").err(),
+            document.set("next", "a\nb").err()
+        );
+        assert_eq!(document.to_string(), "title = Hello \"you\"\nnext=x\n");
+        // but one with spaces, or that only looks quoted, can be written bare
+        document.set("next", "a b").unwrap();
+        assert_eq!(document.to_string(), "title = Hello \"you\"\nnext=a b\n");
+        document.set("next", "say \"hi\"").unwrap();
+        assert_eq!(document.get("next").unwrap(), "say \"hi\"");
+
+        // single quotes are used when double quotes are not allowed
+        let config = config.quotes(&[Quote::Single]);
+        let mut document = Document::parse_with_config("a=1\n", &config).unwrap();
+        document.set("b", "x y").unwrap();
+        assert_eq!(document.to_string(), "a=1\nb='x y'\n");
+        assert!(document.set("b", "it's\nso").is_err());
+
+        let config = ParserConfig::new().line_continuation(true);
+        const CONTINUED: &str = "a=one\\\n  two \\\n b=2";
+        let document = Document::parse_with_config(CONTINUED, &config).unwrap();
+        assert_eq!(document.to_string(), CONTINUED);
+        assert_eq!(document.len(), 2);
+        assert_eq!(document.get("a").unwrap(), "onetwo");
+        assert_eq!(document.pairs().next().unwrap().raw_value(), "one\\\n  two");
+    }
+
+    #[test]
+    fn test_flags() {
+        let config = ParserConfig::new().flags(true);
+        const DATA: &str = "quiet  level = 2 ro # note\n";
+        let mut document = Document::parse_with_config(DATA, &config).unwrap();
+        assert_eq!(document.to_string(), DATA);
+        assert_eq!(document.len(), 1);
+        assert_eq!(document.flags().collect::<Vec<_>>(), ["quiet", "ro"]);
+        assert!(document.is_flag("ro"));
+        assert!(document.get("ro").is_none());
+
+        document.push(Pair::flag("debug"));
+        document.set("quiet", "yes").unwrap();
+        assert!(document.remove("ro"));
+        assert_eq!(document.to_string(), "quiet=yes  level = 2 # note\ndebug\n");
+        assert_eq!(document.get("quiet").unwrap(), "yes");
+        assert_eq!(document.flags().collect::<Vec<_>>(), ["debug"]);
+
+        let config = config.delimiter(Delimiter::Char(','));
+        const LIST: &str = "a=1, quiet ,b=2";
+        let document = Document::parse_with_config(LIST, &config).unwrap();
+        assert_eq!(document.to_string(), LIST);
+        assert!(document.is_flag("quiet"));
+
+        // and they are errors by default
+        assert_eq!(
+            Document::parse("quiet a=1").err(),
+            crate::full_copy::Parser::new("quiet a=1").err()
+        );
+    }
+
+    #[test]
+    fn test_values_are_read_in_the_dialect() {
+        let config = ParserConfig::new().quotes(&[Quote::Single]);
+        let document = Document::parse_with_config("a=\"x\" b='y'", &config).unwrap();
+        assert_eq!(document.get("a").unwrap(), "\"x\"");
+        assert_eq!(document.get("b").unwrap(), "y");
+
+        // a pair from a lax document is not valid in a strict one
+        let lax = Document::parse("odd=\"\\q\"").unwrap();
+        let odd = lax.pairs().next().unwrap().clone();
+        assert_eq!(odd.value().unwrap(), "q");
+        let mut strict =
+            Document::parse_with_config("", &ParserConfig::new().strict_escapes(true)).unwrap();
+        strict.push(odd);
+        let odd = strict.pairs().next().unwrap();
+        assert!(matches!(odd.value(), Err(ParseError::InvalidEscape(_))));
+        assert!(strict.get("odd").is_none());
+    }
+
+    #[test]
+    fn test_errors_match_the_other_strategies() {
+        for data in ["a=1 b", "a=1 =2", "a=\"1", "a=1 b;=2"] {
+            let expected = crate::full_almost_zero_copy::Parser::new(data)
+                .err()
+                .unwrap();
+            assert_eq!(Document::parse(data).err().unwrap(), expected);
+        }
+    }
+}
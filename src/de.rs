@@ -0,0 +1,338 @@
+//! de reads key value text into any type that implements serde's `Deserialize`.
+//!
+//! The input is parsed with [`full_almost_zero_copy`](crate::full_almost_zero_copy), so
+//! values are borrowed from the input unless they had to be unescaped.  A `&str` field
+//! borrows its value, which fails if the value had escapes in it.  A `Cow<str>` field marked
+//! `#[serde(borrow)]` borrows when it can and takes the unescaped `String` when it cannot.
+//!
+//! Numbers, bools, chars and unit enum variants are parsed from the text of the value.  An
+//! `Option` field is `None` when its key is missing.
+//!
+//! ```
+//! use serde::Deserialize;
+//! use std::borrow::Cow;
+//!
+//! #[derive(Deserialize)]
+//! struct Service<'a> {
+//!     host: &'a str,
+//!     port: u16,
+//!     #[serde(borrow)]
+//!     banner: Cow<'a, str>,
+//!     debug: Option<bool>,
+//! }
+//!
+//! const DATA: &str = "host=localhost port=8080 banner=\"say \\\"hi\\\"\"";
+//! let service: Service = key_value_parser::from_str(DATA).unwrap();
+//! assert_eq!(service.host, "localhost");
+//! assert_eq!(service.port, 8080);
+//! assert_eq!(service.banner, "say \"hi\"");
+//! assert_eq!(service.debug, None);
+//! ```
+use serde::de::{
+    self, value::BorrowedStrDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::fmt;
+
+use crate::full_almost_zero_copy::{self, StringOrStr};
+use crate::ParseError;
+
+/// Why the input could not be deserialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input is not valid key value text
+    Parse(ParseError),
+    /// The pairs do not fit the type, for example a missing field or a value that is not a
+    /// number
+    Message(String),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => e.fmt(f),
+            Error::Message(msg) => f.write_str(msg),
+        }
+    }
+}
+impl std::error::Error for Error {}
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+/// Deserializes a `T` from key value text.
+pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T, Error> {
+    T::deserialize(Deserializer::from_str(input)?)
+}
+
+/// A serde `Deserializer` over the pairs of a parsed input.  It deserializes structs and
+/// maps.
+pub struct Deserializer<'de> {
+    parser: full_almost_zero_copy::Parser<'de>,
+}
+impl<'de> Deserializer<'de> {
+    /// Parses the input.  If the input cannot be parsed, an error will be returned.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> Result<Self, ParseError> {
+        Ok(Self::from(full_almost_zero_copy::Parser::new(input)?))
+    }
+}
+impl<'de> From<full_almost_zero_copy::Parser<'de>> for Deserializer<'de> {
+    fn from(parser: full_almost_zero_copy::Parser<'de>) -> Self {
+        Self { parser }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(Pairs {
+            pairs: self.parser.into_iter(),
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Hands the pairs to a map or struct visitor.
+struct Pairs<'de, I> {
+    pairs: I,
+    value: Option<Value<'de>>,
+}
+impl<'de, I> MapAccess<'de> for Pairs<'de, I>
+where
+    I: Iterator<Item = (&'de str, StringOrStr<'de>)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.pairs.next() {
+            Some((key, value)) => {
+                self.value = Some(Value { key, value });
+                seed.deserialize(BorrowedStrDeserializer::new(key))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::Message("value requested before its key".to_string()))?;
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.pairs.size_hint().1
+    }
+}
+
+/// The value of one pair.  The key is kept to name it in errors.
+struct Value<'de> {
+    key: &'de str,
+    value: StringOrStr<'de>,
+}
+impl Value<'_> {
+    fn parse<T>(&self) -> Result<T, Error>
+    where
+        T: std::str::FromStr,
+        T::Err: fmt::Display,
+    {
+        self.value.parse().map_err(|e| {
+            Error::Message(format!(
+                "invalid value `{}` for `{}`: {e}",
+                self.value, self.key
+            ))
+        })
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            StringOrStr::Str(s) => visitor.visit_borrowed_str(s),
+            StringOrStr::String(s) => visitor.visit_string(s),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            StringOrStr::Str(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            StringOrStr::String(s) => visitor.visit_byte_buf(s.into_bytes()),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// A value that is present is always `Some`, a missing key is what makes it `None`
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Only unit variants can be written as a value, by their name
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            StringOrStr::Str(s) => visitor.visit_enum(BorrowedStrDeserializer::new(s)),
+            StringOrStr::String(s) => visitor.visit_enum(s.into_deserializer()),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        str string unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Debug,
+        Info,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Record<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        note: Cow<'a, str>,
+        count: i64,
+        ratio: f64,
+        enabled: bool,
+        level: Level,
+        initial: char,
+        missing: Option<u8>,
+        present: Option<u8>,
+        owned: String,
+    }
+
+    #[test]
+    fn test_struct() {
+        const DATA: &str = "name=svc note=\"a \\\"b\\\"\" count=-3 ratio=0.5 enabled=true \
+                            level=info initial=x present=7 owned=\"o k\"";
+        let record: Record = from_str(DATA).unwrap();
+
+        assert_eq!(
+            record,
+            Record {
+                name: "svc",
+                note: Cow::Owned("a \"b\"".to_string()),
+                count: -3,
+                ratio: 0.5,
+                enabled: true,
+                level: Level::Info,
+                initial: 'x',
+                missing: None,
+                present: Some(7),
+                owned: "o k".to_string(),
+            }
+        );
+        assert!(matches!(record.note, Cow::Owned(_)));
+        // borrowed straight from the input
+        assert_eq!(record.name.as_ptr(), DATA[5..].as_ptr());
+    }
+
+    #[test]
+    fn test_borrows_when_it_can() {
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            #[serde(borrow)]
+            note: Cow<'a, str>,
+        }
+
+        let borrowed: Borrowed = from_str("note=\"plain text\"").unwrap();
+        assert!(matches!(borrowed.note, Cow::Borrowed("plain text")));
+
+        let level: HashMap<&str, Level> = from_str("a=debug b=info").unwrap();
+        assert_eq!(level["a"], Level::Debug);
+    }
+
+    #[test]
+    fn test_errors() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Port {
+            port: u16,
+        }
+
+        let err = from_str::<Port>("port=http").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value `http` for `port`: invalid digit found in string"
+        );
+        let err = from_str::<Port>("other=1").unwrap_err();
+        assert_eq!(err.to_string(), "missing field `port`");
+        let err = from_str::<Port>("port").unwrap_err();
+        assert!(matches!(err, Error::Parse(ParseError::MissingEquals(_))));
+
+        // a &str cannot hold a value that had to be unescaped
+        assert!(from_str::<HashMap<&str, &str>>("a=\"\\\"\"").is_err());
+        assert!(from_str::<HashMap<&str, Level>>("a=trace").is_err());
+    }
+}
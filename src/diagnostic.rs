@@ -0,0 +1,205 @@
+//! Renders a [`ParseError`] against the input it came from.
+//!
+//! The offending line is printed with the failing span underlined and a hint on how to fix it.
+//!
+//! ```
+//! use key_value_parser::full_copy::Parser;
+//!
+//! const DATA: &str = "one=1\ntwo=\"unterminated";
+//! let err = Parser::new(DATA).err().unwrap();
+//! assert_eq!(
+//!     err.diagnostic(DATA).to_string(),
+//!     "\
+//! error: unterminated quoted value
+//!  --> line 2, column 5
+//!   |
+//! 2 | two=\"unterminated
+//!   |     ^^^^^^^^^^^^^
+//!   = hint: did you forget a closing quote?
+//! "
+//! );
+//! ```
+use std::fmt;
+
+use crate::config::ParserConfig;
+use crate::grammar;
+use crate::ParseError;
+
+/// A [`ParseError`] paired with its input, ready to be displayed.
+pub struct Diagnostic<'e, 'i> {
+    error: &'e ParseError,
+    input: &'i str,
+    config: ParserConfig,
+}
+
+impl ParseError {
+    /// Pairs the error with the input it came from, so that it can be rendered with the
+    /// offending line of input.  `input` must be the input that failed to parse.
+    pub fn diagnostic<'e, 'i>(&'e self, input: &'i str) -> Diagnostic<'e, 'i> {
+        self.diagnostic_with_config(input, &ParserConfig::default())
+    }
+
+    /// Pairs the error with the input it came from, and the configuration it was parsed
+    /// with, so that the span of a key is underlined by the configured key characters.
+    pub fn diagnostic_with_config<'e, 'i>(
+        &'e self,
+        input: &'i str,
+        config: &ParserConfig,
+    ) -> Diagnostic<'e, 'i> {
+        Diagnostic {
+            error: self,
+            input,
+            config: config.clone(),
+        }
+    }
+
+    /// A suggestion on how to fix the input, if there is one
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ParseError::MissingEquals(_) => {
+                Some("keys must be followed by the separator, `=` unless configured, and a value")
+            }
+            ParseError::MissingDelimiter(_) => {
+                Some("pairs must be separated by the configured delimiter")
+            }
+            ParseError::UnterminatedQuote(_) => Some("did you forget a closing quote?"),
+            ParseError::UnterminatedHeredoc(_) => {
+                Some("close the heredoc with a line that holds only its tag")
+            }
+            ParseError::DanglingEscape(_) => Some("use `\\\\` for a literal backslash"),
+            ParseError::InvalidEscape(_) => Some(
+                "escapes are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\\"`, `\\xHH` and `\\u{XXXX}`",
+            ),
+            ParseError::EmptyKey(_) => Some("add a key in front of the separator"),
+            ParseError::InvalidKeyChar(_) => {
+                Some("keys may only contain letters, digits and the configured key characters")
+            }
+            ParseError::KeyTooShort(_) => Some("keys must have at least the configured length"),
+            ParseError::KeyTooLong(_) => Some("keys may have at most the configured length"),
+            ParseError::ContinuedValue(_) => {
+                Some("use a strategy that copies values, such as `almost_zero_copy`, to join lines")
+            }
+            ParseError::DuplicateKey(_) => Some("each key may only appear once"),
+            ParseError::Unexpected(_) => None,
+        }
+    }
+}
+
+impl Diagnostic<'_, '_> {
+    /// The line holding the error, and the byte range of the failing span within that line
+    fn line_and_span(&self) -> (&str, usize, usize) {
+        let offset = self.error.offset();
+        let line_start = self.input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.input[offset..]
+            .find('\n')
+            .map_or(self.input.len(), |i| offset + i);
+        let line = self.input[line_start..line_end].trim_end_matches('\r');
+
+        let start = offset - line_start;
+        let end = match self.error {
+            // underline the whole rest of the line that the quote swallowed
+            ParseError::UnterminatedQuote(_) | ParseError::UnterminatedHeredoc(_) => line.len(),
+            // underline the repeated key
+            ParseError::DuplicateKey(_) => line[start..]
+                .find(|c| !grammar::is_key_char(c, &self.config))
+                .map_or(line.len(), |i| start + i),
+            // underline the one character at fault
+            _ => line[start..]
+                .chars()
+                .next()
+                .map_or(start, |c| start + c.len_utf8()),
+        };
+        (line, start, end.max(start))
+    }
+}
+
+impl fmt::Display for Diagnostic<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = self.error.position();
+        let (line, start, end) = self.line_and_span();
+
+        let gutter = position.line.to_string();
+        let pad = " ".repeat(gutter.len());
+
+        // keep tabs so the underline lines up with the text above it
+        let indent: String = line[..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline = "^".repeat(line[start..end].chars().count().max(1));
+
+        writeln!(f, "error: {}", self.error.description())?;
+        writeln!(f, "{pad}--> {position}")?;
+        writeln!(f, "{pad} |")?;
+        writeln!(f, "{gutter} | {line}")?;
+        writeln!(f, "{pad} | {indent}{underline}")?;
+        if let Some(hint) = self.error.hint() {
+            writeln!(f, "{pad} = hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{DuplicatePolicy, ParserConfig};
+    use crate::{full_almost_zero_copy, full_copy, zero_copy};
+
+    #[test]
+    fn test_key_value_error() {
+        const DATA: &str = "one=1 t;wo=2";
+        let err = zero_copy::Parser::new(DATA).err().unwrap();
+
+        assert_eq!(
+            err.diagnostic(DATA).to_string(),
+            "\
+error: invalid character in key
+ --> line 1, column 8
+  |
+1 | one=1 t;wo=2
+  |        ^
+  = hint: keys may only contain letters, digits and the configured key characters
+"
+        );
+    }
+
+    #[test]
+    fn test_error_at_end_of_input() {
+        const DATA: &str = "one=1\n\ttwo";
+        let err = full_almost_zero_copy::Parser::new(DATA).err().unwrap();
+
+        assert_eq!(
+            err.diagnostic(DATA).to_string(),
+            "\
+error: expected separator after key
+ --> line 2, column 5
+  |
+2 | \ttwo
+  | \t   ^
+  = hint: keys must be followed by the separator, `=` unless configured, and a value
+"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_key_with_another_separator() {
+        const DATA: &str = "a.b:1 a.b:2";
+        let config = ParserConfig::new()
+            .separator(':')
+            .key_chars(&['.'])
+            .duplicate_policy(DuplicatePolicy::Error);
+        let err = full_copy::Parser::with_config(DATA, &config).err().unwrap();
+
+        assert_eq!(
+            err.diagnostic_with_config(DATA, &config).to_string(),
+            "\
+error: duplicate key
+ --> line 1, column 7
+  |
+1 | a.b:1 a.b:2
+  |       ^^^
+  = hint: each key may only appear once
+"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Errors reported when the input can not be parsed.
+//!
+//! Every [`ParseError`] knows what went wrong and where it went wrong, so callers can act on
+//! the kind of error without matching on strings.
+//!
+//! ```
+//! use key_value_parser::{full_copy::Parser, ParseError};
+//!
+//! let err = Parser::new("one=1\ntwo=\"unterminated").err().unwrap();
+//! assert!(matches!(err, ParseError::UnterminatedQuote(_)));
+//! assert_eq!(err.position().offset, 10);
+//! assert_eq!(err.position().line, 2);
+//! assert_eq!(err.position().column, 5);
+//! ```
+use std::fmt;
+use std::str::FromStr;
+
+/// A location in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// Byte offset from the start of the input
+    pub offset: usize,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column number in characters, starting at 1
+    pub column: usize,
+}
+impl Position {
+    /// Computes the line and column of a byte offset into the input.
+    pub fn new(input: &str, offset: usize) -> Self {
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// The reasons the input could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A key was not followed by `=`, or the configured separator.  Points where the separator
+    /// was expected.
+    MissingEquals(Position),
+    /// A pair was not followed by the configured [`Delimiter`](crate::config::Delimiter).
+    /// Points at what came instead.
+    MissingDelimiter(Position),
+    /// A quoted value was never closed.  Points at the opening quote.
+    UnterminatedQuote(Position),
+    /// A heredoc was never closed by a line holding only its tag.  Points at the `<<`.
+    UnterminatedHeredoc(Position),
+    /// A quoted value ended in a backslash with nothing to escape.  Points at the backslash.
+    DanglingEscape(Position),
+    /// A quoted value held an escape that could not be decoded, or an unknown one under
+    /// [`ParserConfig::strict_escapes`](crate::config::ParserConfig::strict_escapes).  Points at
+    /// the backslash.
+    InvalidEscape(Position),
+    /// A separator had no key in front of it.  Points at the separator.
+    EmptyKey(Position),
+    /// A key contained a character that is not allowed in keys.  Points at the character.
+    InvalidKeyChar(Position),
+    /// A key was shorter than [`ParserConfig::min_key_len`](crate::config::ParserConfig::min_key_len).
+    /// Points at the key.
+    KeyTooShort(Position),
+    /// A key was longer than [`ParserConfig::max_key_len`](crate::config::ParserConfig::max_key_len).
+    /// Points at the key.
+    KeyTooLong(Position),
+    /// A bare value went on to the next line under
+    /// [`ParserConfig::line_continuation`](crate::config::ParserConfig::line_continuation), in a
+    /// strategy that can only hand out slices of the input and so cannot join its lines.
+    /// Points at the value.
+    ContinuedValue(Position),
+    /// A key appeared more than once under [`DuplicatePolicy::Error`](crate::config::DuplicatePolicy::Error).
+    /// Points at the second occurrence.
+    DuplicateKey(Position),
+    /// The input did not match the grammar in any other way.
+    Unexpected(Position),
+}
+impl ParseError {
+    /// Where in the input the error happened
+    pub fn position(&self) -> Position {
+        match *self {
+            ParseError::MissingEquals(p)
+            | ParseError::MissingDelimiter(p)
+            | ParseError::UnterminatedQuote(p)
+            | ParseError::UnterminatedHeredoc(p)
+            | ParseError::DanglingEscape(p)
+            | ParseError::InvalidEscape(p)
+            | ParseError::EmptyKey(p)
+            | ParseError::InvalidKeyChar(p)
+            | ParseError::KeyTooShort(p)
+            | ParseError::KeyTooLong(p)
+            | ParseError::ContinuedValue(p)
+            | ParseError::DuplicateKey(p)
+            | ParseError::Unexpected(p) => p,
+        }
+    }
+
+    /// Byte offset into the input where the error happened
+    pub fn offset(&self) -> usize {
+        self.position().offset
+    }
+
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            ParseError::MissingEquals(_) => "expected separator after key",
+            ParseError::MissingDelimiter(_) => "expected a delimiter between pairs",
+            ParseError::UnterminatedQuote(_) => "unterminated quoted value",
+            ParseError::UnterminatedHeredoc(_) => "unterminated heredoc",
+            ParseError::DanglingEscape(_) => "backslash at end of quoted value",
+            ParseError::InvalidEscape(_) => "invalid escape sequence",
+            ParseError::EmptyKey(_) => "empty key",
+            ParseError::InvalidKeyChar(_) => "invalid character in key",
+            ParseError::KeyTooShort(_) => "key is too short",
+            ParseError::KeyTooLong(_) => "key is too long",
+            ParseError::ContinuedValue(_) => "value continued onto the next line",
+            ParseError::DuplicateKey(_) => "duplicate key",
+            ParseError::Unexpected(_) => "unexpected input",
+        }
+    }
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.description(), self.position())
+    }
+}
+impl std::error::Error for ParseError {}
+
+/// The reasons a value could not be read as a type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    /// The key is not present
+    Missing { key: String },
+    /// The value does not parse as the type it was asked for
+    Invalid {
+        key: String,
+        value: String,
+        /// The name of the type the value was asked for
+        type_name: &'static str,
+        /// Why the value does not parse
+        reason: String,
+    },
+    /// The input could not be parsed far enough to find the key
+    Parse { key: String, error: ParseError },
+}
+impl ValueError {
+    /// The key that was looked up
+    pub fn key(&self) -> &str {
+        match self {
+            ValueError::Missing { key }
+            | ValueError::Invalid { key, .. }
+            | ValueError::Parse { key, .. } => key,
+        }
+    }
+}
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::Missing { key } => write!(f, "missing value for `{key}`"),
+            ValueError::Invalid {
+                key,
+                value,
+                type_name,
+                reason,
+            } => write!(
+                f,
+                "invalid value `{value}` for `{key}`, expected {type_name}: {reason}"
+            ),
+            ValueError::Parse { key, error } => write!(f, "cannot look up `{key}`: {error}"),
+        }
+    }
+}
+impl std::error::Error for ValueError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ValueError::Parse { error, .. } => Some(error),
+            ValueError::Missing { .. } | ValueError::Invalid { .. } => None,
+        }
+    }
+}
+
+/// Parses the value of `key` as a `T`, naming both in the error if it does not parse.
+pub(crate) fn parse_as<T>(key: &str, value: &str) -> Result<T, ValueError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    interpret(key, value, std::any::type_name::<T>(), str::parse)
+}
+
+/// Interprets the value of `key` with `f`, naming the key, the value and `type_name` in the
+/// error if it fails.
+pub(crate) fn interpret<T, E: fmt::Display>(
+    key: &str,
+    value: &str,
+    type_name: &'static str,
+    f: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<T, ValueError> {
+    f(value).map_err(|e| ValueError::Invalid {
+        key: key.to_string(),
+        value: value.to_string(),
+        type_name,
+        reason: e.to_string(),
+    })
+}
+
+/// The error type of the nom parsers in this crate.
+///
+/// It holds on to the remaining input at the point of failure.  Once the whole input is known,
+/// that is turned into a [`ParseError`] with a line and column.
+#[derive(Debug, Clone)]
+pub struct NomError<'a> {
+    input: &'a str,
+    kind: fn(Position) -> ParseError,
+}
+impl<'a> NomError<'a> {
+    pub(crate) fn new(input: &'a str, kind: fn(Position) -> ParseError) -> Self {
+        Self { input, kind }
+    }
+
+    /// Converts this into a [`ParseError`].  `input` must be the input that was handed to the
+    /// parser, the error refers to a position within it.
+    pub fn into_parse_error(self, input: &str) -> ParseError {
+        (self.kind)(Position::new(input, input.len() - self.input.len()))
+    }
+}
+impl<'a> nom::error::ParseError<&'a str> for NomError<'a> {
+    fn from_error_kind(input: &'a str, _kind: nom::error::ErrorKind) -> Self {
+        Self::new(input, ParseError::Unexpected)
+    }
+
+    fn append(_input: &'a str, _kind: nom::error::ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+/// Result type of the nom parsers in this crate
+pub type NomResult<'a, T> = nom::IResult<&'a str, T, NomError<'a>>;
+
+/// Fails with `kind`, reported at `input`.
+pub(crate) fn fail<'a, T>(input: &'a str, kind: fn(Position) -> ParseError) -> NomResult<'a, T> {
+    Err(nom::Err::Error(NomError::new(input, kind)))
+}
+
+/// Replaces the error of a failed parser with `kind`, reported at `at`.
+pub(crate) fn expect<'a, T>(
+    at: &'a str,
+    kind: fn(Position) -> ParseError,
+    result: NomResult<'a, T>,
+) -> NomResult<'a, T> {
+    result.or_else(|_| fail(at, kind))
+}
+
+/// Converts the outcome of a nom parser into a [`ParseError`] positioned within `input`.
+pub(crate) fn to_parse_error(input: &str, e: nom::Err<NomError<'_>>) -> ParseError {
+    match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.into_parse_error(input),
+        nom::Err::Incomplete(_) => ParseError::Unexpected(Position::new(input, input.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        const DATA: &str = "one=1\ntwo=ü three";
+
+        assert_eq!(
+            Position::new(DATA, 0),
+            Position {
+                offset: 0,
+                line: 1,
+                column: 1
+            }
+        );
+        assert_eq!(
+            Position::new(DATA, 6),
+            Position {
+                offset: 6,
+                line: 2,
+                column: 1
+            }
+        );
+        // ü is two bytes, but one column
+        assert_eq!(Position::new(DATA, 13).column, 7);
+    }
+
+    #[test]
+    fn test_display() {
+        let err = ParseError::MissingEquals(Position::new("one\ntwo", 7));
+        assert_eq!(
+            err.to_string(),
+            "expected separator after key at line 2, column 4"
+        );
+        let err = ParseError::InvalidEscape(Position::new("a=\"\\q\"", 3));
+        assert_eq!(
+            err.to_string(),
+            "invalid escape sequence at line 1, column 4"
+        );
+    }
+}
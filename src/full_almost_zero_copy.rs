@@ -1,15 +1,42 @@
-use anyhow::Result;
-use nom::{
-    bytes::complete::{tag, take, take_while},
-    character::complete::multispace0,
-    IResult,
-};
-use std::collections::HashMap;
-
+use nom::bytes::complete::{take, take_while};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{hash_map, HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, Index};
+
+use crate::config::ParserConfig;
+use crate::error::{expect, to_parse_error, NomResult, ParseError, Position};
+use crate::grammar::{self, ValueKind};
+use crate::{parser, KeyValueParser, Span};
+
+/// A value that is either borrowed from the input, or owned because it had to be unescaped.
+///
+/// It behaves like a `str` either way: it derefs to one, and compares, orders, hashes and
+/// displays like one.
+#[derive(Debug, Clone)]
 pub enum StringOrStr<'a> {
     String(String),
     Str(&'a str),
 }
+impl StringOrStr<'_> {
+    /// The value as a string slice
+    pub fn as_str(&self) -> &str {
+        match self {
+            StringOrStr::String(s) => s.as_str(),
+            StringOrStr::Str(s) => s,
+        }
+    }
+
+    /// Converts the value into an owned `String`, allocating only if it was borrowed
+    pub fn into_owned(self) -> String {
+        match self {
+            StringOrStr::String(s) => s,
+            StringOrStr::Str(s) => s.to_string(),
+        }
+    }
+}
 impl<'a> From<&'a str> for StringOrStr<'a> {
     fn from(value: &'a str) -> Self {
         StringOrStr::Str(value)
@@ -20,17 +47,95 @@ impl From<String> for StringOrStr<'_> {
         StringOrStr::String(value)
     }
 }
+impl From<StringOrStr<'_>> for String {
+    fn from(value: StringOrStr<'_>) -> Self {
+        value.into_owned()
+    }
+}
 impl AsRef<str> for StringOrStr<'_> {
     fn as_ref(&self) -> &str {
-        match self {
-            StringOrStr::String(s) => s.as_str(),
-            StringOrStr::Str(s) => s,
-        }
+        self.as_str()
+    }
+}
+impl Deref for StringOrStr<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+impl Borrow<str> for StringOrStr<'_> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+impl fmt::Display for StringOrStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+/// Hashes like a `str`, so that it can be looked up by `&str` through [`Borrow`]
+impl Hash for StringOrStr<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+/// Borrowed and owned values are equal if their text is equal
+impl PartialEq for StringOrStr<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+impl Eq for StringOrStr<'_> {}
+impl PartialOrd for StringOrStr<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for StringOrStr<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+impl PartialEq<str> for StringOrStr<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+impl PartialEq<&str> for StringOrStr<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+impl PartialEq<String> for StringOrStr<'_> {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+impl PartialEq<StringOrStr<'_>> for str {
+    fn eq(&self, other: &StringOrStr<'_>) -> bool {
+        self == other.as_str()
+    }
+}
+impl PartialEq<StringOrStr<'_>> for &str {
+    fn eq(&self, other: &StringOrStr<'_>) -> bool {
+        *self == other.as_str()
     }
 }
 
+/// Iterator over the pairs of a [`Parser`], see [`Parser::iter`]
+pub type Iter<'s, 'a> = std::iter::Map<
+    hash_map::Iter<'s, &'a str, StringOrStr<'a>>,
+    fn((&'s &'a str, &'s StringOrStr<'a>)) -> (&'a str, &'s str),
+>;
+
+#[derive(Debug, Clone, Default)]
 pub struct Parser<'a> {
     map: HashMap<&'a str, StringOrStr<'a>>,
+    duplicates: HashMap<&'a str, Vec<StringOrStr<'a>>>,
+    flags: HashSet<&'a str>,
+    input: &'a str,
+    config: ParserConfig,
 }
 impl<'a> Parser<'a> {
     /// Construct a new parser.  
@@ -42,29 +147,140 @@ impl<'a> Parser<'a> {
     /// assert_eq!(parser.len(), 1);
     /// assert_eq!(parser.get("key").unwrap(), "value");
     /// ```
-    pub fn new(input: &'a str) -> Result<Self> {
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        Self::with_config(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration.
+    /// If the parser cannot parse the input, an error will be returned.
+    pub fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        let (parser, errors) = Self::parse(input, config, false);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(parser),
+        }
+    }
+
+    /// Construct a new parser that skips over pairs it cannot parse.
+    /// Every pair that fails to parse is skipped up to the next whitespace, and its error
+    /// is returned alongside the pairs that did parse.
+    /// ```
+    /// use key_value_parser::full_almost_zero_copy::Parser;
+    /// const DATA: &str = "one=1 ;two=2 three=3";
+    /// let (parser, errors) = Parser::new_lenient(DATA);
+    /// assert_eq!(parser.len(), 2);
+    /// assert_eq!(parser.get("three").unwrap(), "3");
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].offset(), 6);
+    /// ```
+    pub fn new_lenient(input: &'a str) -> (Self, Vec<ParseError>) {
+        Self::with_config_lenient(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration, that skips over pairs it cannot
+    /// parse.  See [`Parser::new_lenient`].
+    pub fn with_config_lenient(input: &'a str, config: &ParserConfig) -> (Self, Vec<ParseError>) {
+        Self::parse(input, config, true)
+    }
+
+    /// Parses the input, collecting every error.  Unless `lenient` is set, parsing stops
+    /// at the first error.
+    fn parse(input: &'a str, config: &ParserConfig, lenient: bool) -> (Self, Vec<ParseError>) {
         // use nom to parse data
         let mut map = HashMap::new();
+        let mut duplicates = HashMap::new();
+        let mut flags = HashSet::new();
+        let mut errors = Vec::new();
 
-        let mut head = input.trim_start();
+        let mut head = grammar::skip_space(input, config);
         while !head.is_empty() {
-            let (input, (key, value)) = parse_one_key_value(head)
-                .map_err(|e| anyhow::anyhow!("Could not parse input data: {} {:?}", input, e))?;
-
-            map.insert(key, value);
-
-            head = input;
+            match parse_one_key_value(head, config) {
+                Ok((rest, (key, None))) => {
+                    flags.insert(key);
+                    head = rest;
+                }
+                Ok((rest, (key, Some(value)))) => {
+                    let policy = config.duplicate_policy;
+                    if !grammar::insert(&mut map, &mut duplicates, policy, key, value) {
+                        let offset = grammar::offset_in(input, key);
+                        errors.push(ParseError::DuplicateKey(Position::new(input, offset)));
+                        if !lenient {
+                            break;
+                        }
+                    }
+
+                    head = rest;
+                }
+                Err(e) => {
+                    errors.push(to_parse_error(input, e));
+                    if !lenient {
+                        break;
+                    }
+
+                    head = grammar::skip_pair(head, config);
+                }
+            }
         }
 
-        Ok(Self { map })
+        (
+            Self {
+                map,
+                flags,
+                duplicates,
+                input,
+                config: config.clone(),
+            },
+            errors,
+        )
     }
 
     /// Gets a value from the container.  Same signature as HashMap::get
     pub fn get(&self, key: &str) -> Option<&str> {
-        self.map.get(key).map(|v| match v {
-            StringOrStr::String(s) => s.as_str(),
-            StringOrStr::Str(s) => s,
-        })
+        self.map.get(key).map(StringOrStr::as_str)
+    }
+
+    /// Gets every value of a key, in the order they appear in the input.  Only
+    /// [`DuplicatePolicy::Collect`](crate::config::DuplicatePolicy::Collect) keeps more than
+    /// one value per key.
+    pub fn get_all<'s>(&'s self, key: &str) -> impl Iterator<Item = &'s str> {
+        self.map
+            .get(key)
+            .into_iter()
+            .chain(self.duplicates.get(key).into_iter().flatten())
+            .map(AsRef::as_ref)
+    }
+
+    /// Iterates over the key value pairs, in no particular order
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        self.map.iter().map(|(k, v)| (*k, v.as_str()))
+    }
+
+    /// Iterates over the keys, in no particular order
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.map.keys().copied()
+    }
+
+    /// Iterates over the values, in no particular order
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.map.values().map(StringOrStr::as_str)
+    }
+
+    /// Gets where the pair of a key sits in the input.  Pairs that were not parsed from the
+    /// input, such as those added through [`Extend`], have no span.
+    pub fn get_span(&self, key: &str) -> Option<Span> {
+        let (key, _) = self.map.get_key_value(key)?;
+        Span::of(self.input, key, &self.config)
+    }
+
+    /// Returns true if the key appeared on its own, without a value, under
+    /// [`ParserConfig::flags`](crate::config::ParserConfig::flags)
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.flags.contains(key)
+    }
+
+    /// Iterates over the keys that appeared on their own, in no particular order
+    pub fn flags(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.flags.iter().copied()
     }
 
     /// Returns how many key value pairs are available
@@ -78,78 +294,216 @@ impl<'a> Parser<'a> {
     }
 }
 
-fn parse_one_key_value(input: &str) -> IResult<&str, (&str, StringOrStr)> {
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, key) = take_while(|c: char| c.is_alphanumeric() || c == '-' || c == '_')(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, _) = tag("=")(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, value) = parse_value(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
+/// Parsers are equal if they hold the same pairs, whatever input they came from
+impl PartialEq for Parser<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map && self.duplicates == other.duplicates && self.flags == other.flags
+    }
+}
+impl Eq for Parser<'_> {}
 
-    Ok((input, (key, value)))
+impl<'s, 'a> IntoIterator for &'s Parser<'a> {
+    type Item = (&'a str, &'s str);
+    type IntoIter = Iter<'s, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
-fn unquoted_value(input: &str) -> IResult<&str, StringOrStr> {
-    let (input, value) = take_while(|c: char| !c.is_whitespace())(input)?;
-    Ok((input, value.into()))
+impl<'a> IntoIterator for Parser<'a> {
+    type Item = (&'a str, StringOrStr<'a>);
+    type IntoIter = hash_map::IntoIter<&'a str, StringOrStr<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+/// Panics if the key is not present, like indexing a `HashMap`
+impl Index<&str> for Parser<'_> {
+    type Output = str;
+
+    fn index(&self, key: &str) -> &str {
+        self.get(key).expect("key not found")
+    }
+}
+
+/// Later pairs replace earlier ones with the same key
+impl<'a> FromIterator<(&'a str, StringOrStr<'a>)> for Parser<'a> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, StringOrStr<'a>)>>(iter: I) -> Self {
+        let mut parser = Self::default();
+        parser.extend(iter);
+        parser
+    }
+}
+
+/// Later pairs replace earlier ones with the same key
+impl<'a> Extend<(&'a str, StringOrStr<'a>)> for Parser<'a> {
+    fn extend<I: IntoIterator<Item = (&'a str, StringOrStr<'a>)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            grammar::replace(&mut self.map, key, value);
+        }
+    }
 }
 
-fn quoted_value(input: &str) -> IResult<&str, StringOrStr> {
-    let (input, _) = tag("\"")(input)?;
+impl<'a> KeyValueParser<'a> for Parser<'a> {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
+        Parser::new(input)
+    }
+
+    fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        Parser::with_config(input, config)
+    }
+
+    fn get(&self, key: &str) -> Option<StringOrStr<'_>> {
+        Parser::get(self, key).map(StringOrStr::Str)
+    }
+
+    fn get_span(&self, key: &str) -> Option<Span> {
+        Parser::get_span(self, key)
+    }
+
+    fn is_flag(&self, key: &str) -> bool {
+        Parser::is_flag(self, key)
+    }
+
+    fn len(&self) -> usize {
+        Parser::len(self)
+    }
+
+    fn iter(&self) -> parser::Iter<'_> {
+        Box::new(
+            self.map
+                .iter()
+                .map(|(k, v)| (*k, StringOrStr::Str(v.as_ref()))),
+        )
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+}
+
+pub(crate) fn parse_one_key_value<'a>(
+    input: &'a str,
+    config: &ParserConfig,
+) -> NomResult<'a, (&'a str, Option<StringOrStr<'a>>)> {
+    // eat whitespace and comments
+    let input = grammar::skip_space(input, config);
+    if let Some((input, key)) = grammar::flag(input, config) {
+        let (input, _) = grammar::delimiter(input, config)?;
+        return Ok((input, (key, None)));
+    }
+    let (input, key) = grammar::key_and_separator(input, config)?;
+    let (input, value) = parse_value_with_config(input, config)?;
+    // eat the delimiter, and the whitespace and comments around it
+    let (input, _) = grammar::delimiter(input, config)?;
+
+    Ok((input, (key, Some(value))))
+}
+
+fn quoted_value<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, StringOrStr<'a>> {
+    let start = input;
+    let (input, close) = grammar::open_quote(input)?;
 
     let mut accum: Option<String> = None;
 
+    // the text since the last escape, which runs past quotes that do not close the value
     let mut head = input;
+    let mut scan = input;
     loop {
         // consume until we hit a backslash or a quote
-        let (input, so_far) = take_while(|c: char| c != '\\' && c != '"')(head)?;
+        let (input, _) = take_while(|c: char| c != '\\' && c != '"')(scan)?;
+        let so_far = &head[..head.len() - input.len()];
 
         // let's see what we hit
-        let (data, backslash_or_quote) = take(1usize)(input)?;
+        let (data, backslash_or_quote) =
+            expect(start, ParseError::UnterminatedQuote, take(1usize)(input))?;
 
         match backslash_or_quote {
-            "\"" => {
-                // we hit a quote
+            "\"" if input.starts_with(close) => {
+                // we hit the closing quote
                 // so we're done
                 let value = match accum {
                     Some(accum) => StringOrStr::String(accum + so_far),
                     None => StringOrStr::Str(so_far),
                 };
-                return Ok((data, value));
+                return Ok((&input[close.len()..], value));
+            }
+            "\"" => {
+                // a quote in triple quotes is part of the value
+                scan = data;
             }
             _ => {
                 // we hit a backslash
-                // so we need to see what's next
-                let (data, next_char) = take(1usize)(data)?;
-                // append that as a literal value
+                // so we need to decode the escape it starts
+                let (data, c) = grammar::escape(input, config.strict_escapes)?;
                 let to_append = accum.get_or_insert_with(String::new);
                 to_append.push_str(so_far);
-                to_append.push_str(next_char);
+                to_append.push(c);
 
                 // move the head forward
                 head = data;
+                scan = data;
             }
         }
     }
 }
 
-pub fn parse_value(input: &str) -> IResult<&str, StringOrStr> {
-    let (_, peek_next_char) = take(1usize)(input)?;
+/// Parses a heredoc.  It stays a slice of the input unless it has indentation to strip.
+fn heredoc_value(input: &str) -> NomResult<'_, StringOrStr<'_>> {
+    let (input, (text, strip_indent)) = grammar::heredoc_value(input)?;
+    let value = match strip_indent.then(|| grammar::dedent(text)) {
+        Some(Some(dedented)) => StringOrStr::String(dedented),
+        _ => StringOrStr::Str(text),
+    };
+    Ok((input, value))
+}
 
-    match peek_next_char {
-        "\"" => quoted_value(input),
-        _ => unquoted_value(input),
-    }
+/// Parses a value, unquoting it if it is quoted.  Escapes are decoded in double quotes, and
+/// in triple double quotes, but not in single quotes or heredocs.  The value is borrowed from
+/// the input unless escapes were decoded, indentation stripped, or lines joined.
+///
+/// ```
+/// use key_value_parser::config::ParserConfig;
+/// use key_value_parser::full_almost_zero_copy::{parse_value, parse_value_with_config};
+///
+/// let (_, value) = parse_value("\"\"\"\nsay \"hi\"\n\"\"\"").unwrap();
+/// assert_eq!(value, "say \"hi\"\n");
+///
+/// const SQL: &str = "<<~EOF\n    SELECT *\n      FROM t\n    EOF";
+/// let (_, value) = parse_value_with_config(SQL, &ParserConfig::new().heredocs(true)).unwrap();
+/// assert_eq!(value, "SELECT *\n  FROM t");
+/// ```
+pub fn parse_value(input: &str) -> NomResult<'_, StringOrStr<'_>> {
+    parse_value_with_config(input, &ParserConfig::default())
+}
+
+/// Parses a value with the given configuration, see [`parse_value`].
+pub fn parse_value_with_config<'a>(
+    input: &'a str,
+    config: &ParserConfig,
+) -> NomResult<'a, StringOrStr<'a>> {
+    let (input, value) = match grammar::value_kind(input, config)? {
+        (input, ValueKind::Quoted | ValueKind::TripleQuoted) => return quoted_value(input, config),
+        (input, ValueKind::Literal) => grammar::literal_value(input)?,
+        (input, ValueKind::Heredoc) => return heredoc_value(input),
+        (input, ValueKind::Unquoted) => {
+            let (input, value) = grammar::unquoted_value(input, config)?;
+            if let Some(joined) = grammar::join_lines(value) {
+                return Ok((input, StringOrStr::String(joined)));
+            }
+            (input, value)
+        }
+    };
+    Ok((input, StringOrStr::Str(value)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Delimiter;
     use anyhow::Result;
 
     #[test]
@@ -209,6 +563,73 @@ mod tests {
         assert_eq!(value, "value with \"escaped\" quotes");
     }
 
+    #[test]
+    fn test_escape_sequences() {
+        const DATA: &str = r#"text="one\ntwo\tthree\r\0" hex="\x41\x7e" braced="\u{1F600}" surrogates="\ud83d\ude00\u00e9" literal="\q""#;
+        let parser = Parser::new(DATA).unwrap();
+
+        assert_eq!(parser.get("text").unwrap(), "one\ntwo\tthree\r\0");
+        assert_eq!(parser.get("hex").unwrap(), "A~");
+        assert_eq!(parser.get("braced").unwrap(), "😀");
+        assert_eq!(parser.get("surrogates").unwrap(), "😀é");
+        assert_eq!(parser.get("literal").unwrap(), "q");
+
+        let err = Parser::new(r#"a="\xZZ""#).err().unwrap();
+        assert!(matches!(err, ParseError::InvalidEscape(p) if p.offset == 3));
+        let err = Parser::new(r#"a="\ud800 lone""#).err().unwrap();
+        assert!(matches!(err, ParseError::InvalidEscape(p) if p.offset == 3));
+    }
+
+    #[test]
+    fn test_single_quotes_are_never_unescaped() {
+        const DATA: &str = r#"literal='C:\temp\new "dir"' escaped="C:\\temp""#;
+        let parser = Parser::new(DATA).unwrap();
+
+        let (_, literal) = parse_value(&DATA[8..]).unwrap();
+        assert!(matches!(literal, StringOrStr::Str(r#"C:\temp\new "dir""#)));
+        assert_eq!(parser.get("literal").unwrap(), r#"C:\temp\new "dir""#);
+        assert_eq!(parser.get("escaped").unwrap(), r"C:\temp");
+    }
+
+    #[test]
+    fn test_multi_line_values() {
+        const DATA: &str = "cert=\"\"\"\n-----BEGIN-----\nMIIB\"x\"\n-----END-----\"\"\"\n\
+                            escaped=\"\"\"a\\tb\"\"\"\n\
+                            script=<<EOF\n#!/bin/sh\necho \"hi\"\nEOF\n\
+                            sql=<<~SQL\n    SELECT *\n\n      FROM t\n    SQL\n\
+                            flat=<<~EOF\nno indent\n  EOF";
+        let config = ParserConfig::new().heredocs(true);
+        let parser = Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.len(), 5);
+
+        let (_, cert) = parse_value(&DATA[5..]).unwrap();
+        assert!(matches!(
+            cert,
+            StringOrStr::Str("-----BEGIN-----\nMIIB\"x\"\n-----END-----")
+        ));
+        let (_, escaped) = parse_value("\"\"\"a\\tb\"\"\"").unwrap();
+        assert!(matches!(escaped, StringOrStr::String(s) if s == "a\tb"));
+        assert_eq!(parser.get("script").unwrap(), "#!/bin/sh\necho \"hi\"");
+        assert_eq!(parser.get("sql").unwrap(), "SELECT *\n\n  FROM t");
+        let (_, flat) = parse_value_with_config("<<~EOF\nno indent\n  EOF", &config).unwrap();
+        assert!(matches!(flat, StringOrStr::Str("no indent")));
+
+        assert!(matches!(
+            Parser::new("a=1 b=\"\"\"open\"\"").err().unwrap(),
+            ParseError::UnterminatedQuote(p) if p.offset == 6
+        ));
+        assert!(matches!(
+            Parser::with_config("a=1 b=<<EOF\ntext\nEOFS", &config).err().unwrap(),
+            ParseError::UnterminatedHeredoc(p) if p.offset == 6
+        ));
+        assert!(matches!(
+            Parser::with_config("b=<<EOF text\nEOF", &config).err().unwrap(),
+            ParseError::Unexpected(p) if p.offset == 8
+        ));
+        let parser = Parser::with_config("a=<< b=<", &config).unwrap();
+        assert_eq!(parser.get("a").unwrap(), "<<");
+    }
+
     #[test]
     fn test_bench_pattern() {
         const DATA: &str = "kkkkkkkkkk2=\"vvvvv\\\"ttttt2\" kkkkkkkkkk3=\"vvvvv\\\"ttttt3\" ";
@@ -237,4 +658,95 @@ mod tests {
             assert!(parser.is_err(), "Should have failed to parse: {:?}", data);
         }
     }
+
+    #[test]
+    fn test_error_kinds() {
+        fn err(data: &str) -> ParseError {
+            Parser::new(data).err().unwrap()
+        }
+
+        assert!(matches!(err(" foo "), ParseError::MissingEquals(p) if p.offset == 5));
+        assert!(matches!(err("foo bar=1"), ParseError::MissingEquals(p) if p.offset == 4));
+        assert!(matches!(err(";foo=bar"), ParseError::InvalidKeyChar(p) if p.offset == 0));
+        assert!(matches!(err("foo.bar=1"), ParseError::InvalidKeyChar(p) if p.offset == 3));
+        assert!(matches!(err("a=1 =2"), ParseError::EmptyKey(p) if p.offset == 4));
+        assert!(
+            matches!(err("a=1\nq=\"foo"), ParseError::UnterminatedQuote(p) if p.line == 2 && p.column == 3)
+        );
+        assert!(matches!(err("q=\"foo\\"), ParseError::DanglingEscape(p) if p.offset == 6));
+    }
+
+    #[test]
+    fn test_lenient_keeps_the_rest_of_the_line() {
+        const DATA: &str = "one=1 two=\"2 2\" bad=\"stray three=3 =4";
+        let (parser, errors) = Parser::new_lenient(DATA);
+
+        // the unterminated quote only costs us its own pair
+        assert_eq!(parser.len(), 3);
+        assert_eq!(parser.get("one").unwrap(), "1");
+        assert_eq!(parser.get("two").unwrap(), "2 2");
+        assert_eq!(parser.get("three").unwrap(), "3");
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], ParseError::UnterminatedQuote(p) if p.offset == 20));
+        assert!(matches!(errors[1], ParseError::EmptyKey(p) if p.offset == 35));
+    }
+
+    #[test]
+    fn test_only_continued_values_are_copied() {
+        const DATA: &str = "path = /usr/bin:\\\n       /bin\nname = plain\n";
+        let config = ParserConfig::new()
+            .delimiter(Delimiter::Newline)
+            .line_continuation(true);
+        let parser = Parser::with_config(DATA, &config).unwrap();
+        for (key, value) in parser {
+            let joined = matches!(value, StringOrStr::String(_));
+            assert_eq!(joined, key == "path", "{key}");
+        }
+    }
+
+    #[test]
+    fn test_string_or_str_behaves_like_str() {
+        use std::collections::HashSet;
+
+        let borrowed = StringOrStr::Str("value");
+        let owned = StringOrStr::String("value".to_string());
+
+        assert_eq!(borrowed, owned);
+        assert_eq!(borrowed, "value");
+        assert_eq!("value", owned);
+        assert_eq!(owned, "value".to_string());
+        assert_eq!(owned.to_string(), "value");
+        assert_eq!(owned.len(), 5);
+        assert_eq!(String::from(borrowed.clone()), "value");
+
+        let set: HashSet<_> = [borrowed, owned].into_iter().collect();
+        assert_eq!(set.len(), 1);
+        assert!(set.contains("value"));
+    }
+
+    #[test]
+    fn test_std_traits() {
+        const DATA: &str = "one=1 two=\"2\\\"\"";
+        let parser = Parser::new(DATA).unwrap();
+
+        assert_eq!(parser.clone(), parser);
+        assert_eq!(&parser["two"], "2\"");
+        assert_eq!(parser.keys().count(), 2);
+        assert!(parser.values().any(|v| v == "1"));
+        assert!(format!("{parser:?}").contains("one"));
+
+        let mut collected: Parser = (&parser)
+            .into_iter()
+            .map(|(k, v)| (k, StringOrStr::from(v.to_string())))
+            .collect();
+        assert_eq!(collected, parser);
+
+        collected.extend([("three", StringOrStr::from("3"))]);
+        assert_eq!(collected.len(), 3);
+        assert_ne!(collected, parser);
+
+        let mut pairs = parser.into_iter().collect::<Vec<_>>();
+        pairs.sort();
+        assert_eq!(pairs, [("one", "1".into()), ("two", "2\"".into())]);
+    }
 }
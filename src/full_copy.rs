@@ -1,13 +1,25 @@
-use anyhow::Result;
-use nom::{
-    bytes::complete::{tag, take, take_while},
-    character::complete::multispace0,
-    IResult,
-};
-use std::collections::HashMap;
-
+use nom::bytes::complete::{take, take_while};
+use std::collections::{hash_map, HashMap, HashSet};
+use std::ops::Index;
+
+use crate::config::ParserConfig;
+use crate::error::{expect, to_parse_error, NomResult, ParseError, Position};
+use crate::grammar::{self, ValueKind};
+use crate::{full_almost_zero_copy::StringOrStr, parser, KeyValueParser, Span};
+
+/// Iterator over the pairs of a [`Parser`], see [`Parser::iter`]
+pub type Iter<'s, 'a> = std::iter::Map<
+    hash_map::Iter<'s, &'a str, String>,
+    fn((&'s &'a str, &'s String)) -> (&'a str, &'s str),
+>;
+
+#[derive(Debug, Clone, Default)]
 pub struct Parser<'a> {
     pub map: HashMap<&'a str, String>,
+    duplicates: HashMap<&'a str, Vec<String>>,
+    flags: HashSet<&'a str>,
+    input: &'a str,
+    config: ParserConfig,
 }
 impl<'a> Parser<'a> {
     /// Construct a new parser.  
@@ -19,26 +31,140 @@ impl<'a> Parser<'a> {
     /// assert_eq!(parser.len(), 1);
     /// assert_eq!(parser.get("key").unwrap(), "value");
     /// ```
-    pub fn new(input: &'a str) -> Result<Self> {
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        Self::with_config(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration.
+    /// If the parser cannot parse the input, an error will be returned.
+    pub fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        let (parser, errors) = Self::parse(input, config, false);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(parser),
+        }
+    }
+
+    /// Construct a new parser that skips over pairs it cannot parse.
+    /// Every pair that fails to parse is skipped up to the next whitespace, and its error
+    /// is returned alongside the pairs that did parse.
+    /// ```
+    /// use key_value_parser::full_copy::Parser;
+    /// const DATA: &str = "one=1 ;two=2 three=3";
+    /// let (parser, errors) = Parser::new_lenient(DATA);
+    /// assert_eq!(parser.len(), 2);
+    /// assert_eq!(parser.get("three").unwrap(), "3");
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].offset(), 6);
+    /// ```
+    pub fn new_lenient(input: &'a str) -> (Self, Vec<ParseError>) {
+        Self::with_config_lenient(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration, that skips over pairs it cannot
+    /// parse.  See [`Parser::new_lenient`].
+    pub fn with_config_lenient(input: &'a str, config: &ParserConfig) -> (Self, Vec<ParseError>) {
+        Self::parse(input, config, true)
+    }
+
+    /// Parses the input, collecting every error.  Unless `lenient` is set, parsing stops
+    /// at the first error.
+    fn parse(input: &'a str, config: &ParserConfig, lenient: bool) -> (Self, Vec<ParseError>) {
         // use nom to parse data
         let mut map = HashMap::new();
+        let mut duplicates = HashMap::new();
+        let mut flags = HashSet::new();
+        let mut errors = Vec::new();
 
-        let mut head = input.trim_start();
+        let mut head = grammar::skip_space(input, config);
         while !head.is_empty() {
-            let (input, (key, value)) = parse_one_key_value(head)
-                .map_err(|e| anyhow::anyhow!("Could not parse input data: {:?}", e))?;
+            match parse_one_key_value(head, config) {
+                Ok((rest, (key, None))) => {
+                    flags.insert(key);
+                    head = rest;
+                }
+                Ok((rest, (key, Some(value)))) => {
+                    let policy = config.duplicate_policy;
+                    if !grammar::insert(&mut map, &mut duplicates, policy, key, value) {
+                        let offset = grammar::offset_in(input, key);
+                        errors.push(ParseError::DuplicateKey(Position::new(input, offset)));
+                        if !lenient {
+                            break;
+                        }
+                    }
+
+                    head = rest;
+                }
+                Err(e) => {
+                    errors.push(to_parse_error(input, e));
+                    if !lenient {
+                        break;
+                    }
+
+                    head = grammar::skip_pair(head, config);
+                }
+            }
+        }
 
-            map.insert(key, value);
+        (
+            Self {
+                map,
+                flags,
+                duplicates,
+                input,
+                config: config.clone(),
+            },
+            errors,
+        )
+    }
 
-            head = input;
-        }
+    /// Gets a value from the container.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.map.get(key).map(String::as_str)
+    }
+
+    /// Gets every value of a key, in the order they appear in the input.  Only
+    /// [`DuplicatePolicy::Collect`](crate::config::DuplicatePolicy::Collect) keeps more than
+    /// one value per key.
+    pub fn get_all<'s>(&'s self, key: &str) -> impl Iterator<Item = &'s str> {
+        self.map
+            .get(key)
+            .into_iter()
+            .chain(self.duplicates.get(key).into_iter().flatten())
+            .map(String::as_str)
+    }
 
-        Ok(Self { map })
+    /// Iterates over the key value pairs, in no particular order
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        self.map.iter().map(|(k, v)| (*k, v.as_str()))
     }
 
-    /// Gets a value from the container.  Same signature as HashMap::get
-    pub fn get(&self, key: &str) -> Option<&String> {
-        self.map.get(key)
+    /// Iterates over the keys, in no particular order
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.map.keys().copied()
+    }
+
+    /// Iterates over the values, in no particular order
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.map.values().map(String::as_str)
+    }
+
+    /// Gets where the pair of a key sits in the input.  Pairs that were not parsed from the
+    /// input, such as those added through [`Extend`], have no span.
+    pub fn get_span(&self, key: &str) -> Option<Span> {
+        let (key, _) = self.map.get_key_value(key)?;
+        Span::of(self.input, key, &self.config)
+    }
+
+    /// Returns true if the key appeared on its own, without a value, under
+    /// [`ParserConfig::flags`](crate::config::ParserConfig::flags)
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.flags.contains(key)
+    }
+
+    /// Iterates over the keys that appeared on their own, in no particular order
+    pub fn flags(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.flags.iter().copied()
     }
 
     /// Returns how many key value pairs are available
@@ -52,29 +178,118 @@ impl<'a> Parser<'a> {
     }
 }
 
-fn parse_one_key_value(input: &str) -> IResult<&str, (&str, String)> {
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, key) = take_while(|c: char| c.is_alphanumeric() || c == '-' || c == '_')(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, _) = tag("=")(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-    let (input, value) = parse_value(input)?;
-    // eat whitespace
-    let (input, _) = multispace0(input)?;
-
-    Ok((input, (key, value)))
+/// Parsers are equal if they hold the same pairs, whatever input they came from
+impl PartialEq for Parser<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map && self.duplicates == other.duplicates && self.flags == other.flags
+    }
+}
+impl Eq for Parser<'_> {}
+
+impl<'s, 'a> IntoIterator for &'s Parser<'a> {
+    type Item = (&'a str, &'s str);
+    type IntoIter = Iter<'s, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
-fn unquoted_value(input: &str) -> IResult<&str, String> {
-    let (input, value) = take_while(|c: char| !c.is_whitespace())(input)?;
-    Ok((input, value.to_string()))
+impl<'a> IntoIterator for Parser<'a> {
+    type Item = (&'a str, String);
+    type IntoIter = hash_map::IntoIter<&'a str, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+/// Panics if the key is not present, like indexing a `HashMap`
+impl Index<&str> for Parser<'_> {
+    type Output = str;
+
+    fn index(&self, key: &str) -> &str {
+        self.get(key).expect("key not found")
+    }
+}
+
+/// Later pairs replace earlier ones with the same key
+impl<'a> FromIterator<(&'a str, String)> for Parser<'a> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, String)>>(iter: I) -> Self {
+        let mut parser = Self::default();
+        parser.extend(iter);
+        parser
+    }
+}
+
+/// Later pairs replace earlier ones with the same key
+impl<'a> Extend<(&'a str, String)> for Parser<'a> {
+    fn extend<I: IntoIterator<Item = (&'a str, String)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            grammar::replace(&mut self.map, key, value);
+        }
+    }
+}
+
+impl<'a> KeyValueParser<'a> for Parser<'a> {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
+        Parser::new(input)
+    }
+
+    fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        Parser::with_config(input, config)
+    }
+
+    fn get(&self, key: &str) -> Option<StringOrStr<'_>> {
+        Parser::get(self, key).map(StringOrStr::Str)
+    }
+
+    fn get_span(&self, key: &str) -> Option<Span> {
+        Parser::get_span(self, key)
+    }
+
+    fn is_flag(&self, key: &str) -> bool {
+        Parser::is_flag(self, key)
+    }
+
+    fn len(&self) -> usize {
+        Parser::len(self)
+    }
+
+    fn iter(&self) -> parser::Iter<'_> {
+        Box::new(
+            self.map
+                .iter()
+                .map(|(k, v)| (*k, StringOrStr::Str(v.as_str()))),
+        )
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
 }
 
-fn quoted_value(input: &str) -> IResult<&str, String> {
-    let (input, _) = tag("\"")(input)?;
+fn parse_one_key_value<'a>(
+    input: &'a str,
+    config: &ParserConfig,
+) -> NomResult<'a, (&'a str, Option<String>)> {
+    // eat whitespace and comments
+    let input = grammar::skip_space(input, config);
+    if let Some((input, key)) = grammar::flag(input, config) {
+        let (input, _) = grammar::delimiter(input, config)?;
+        return Ok((input, (key, None)));
+    }
+    let (input, key) = grammar::key_and_separator(input, config)?;
+    let (input, value) = parse_value(input, config)?;
+    // eat the delimiter, and the whitespace and comments around it
+    let (input, _) = grammar::delimiter(input, config)?;
+
+    Ok((input, (key, Some(value))))
+}
+
+fn quoted_value<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, String> {
+    let start = input;
+    let (input, close) = grammar::open_quote(input)?;
 
     // aaaaaaaaaaaa\"bbbbbbbbbbbbbbbb\"ccccccccccc\"dddddddddd
     let mut accum = String::new();
@@ -87,21 +302,26 @@ fn quoted_value(input: &str) -> IResult<&str, String> {
         accum.push_str(so_far);
 
         // let's see what we hit
-        let (data, backslash_or_quote) = take(1usize)(input)?;
+        let (data, backslash_or_quote) =
+            expect(start, ParseError::UnterminatedQuote, take(1usize)(input))?;
 
         match backslash_or_quote {
-            "\"" => {
-                // we hit a quote
+            "\"" if input.starts_with(close) => {
+                // we hit the closing quote
                 // so we're done
-                head = data;
+                head = &input[close.len()..];
                 break;
             }
+            "\"" => {
+                // a quote in triple quotes is part of the value
+                accum.push('"');
+                head = data;
+            }
             _ => {
                 // we hit a backslash
-                // so we need to see what's next
-                let (data, next_char) = take(1usize)(data)?;
-                // append that as a literal value
-                accum.push_str(next_char);
+                // so we need to decode the escape it starts
+                let (data, c) = grammar::escape(input, config.strict_escapes)?;
+                accum.push(c);
 
                 // move the head forward
                 head = data;
@@ -112,18 +332,32 @@ fn quoted_value(input: &str) -> IResult<&str, String> {
     Ok((head, accum))
 }
 
-fn parse_value(input: &str) -> IResult<&str, String> {
-    let (_, peek_next_char) = take(1usize)(input)?;
-
-    match peek_next_char {
-        "\"" => quoted_value(input),
-        _ => unquoted_value(input),
-    }
+fn parse_value<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, String> {
+    let (input, value) = match grammar::value_kind(input, config)? {
+        (input, ValueKind::Quoted | ValueKind::TripleQuoted) => return quoted_value(input, config),
+        (input, ValueKind::Literal) => grammar::literal_value(input)?,
+        (input, ValueKind::Heredoc) => {
+            let (input, (text, strip_indent)) = grammar::heredoc_value(input)?;
+            match strip_indent.then(|| grammar::dedent(text)) {
+                Some(Some(dedented)) => return Ok((input, dedented)),
+                _ => (input, text),
+            }
+        }
+        (input, ValueKind::Unquoted) => {
+            let (input, value) = grammar::unquoted_value(input, config)?;
+            if let Some(joined) = grammar::join_lines(value) {
+                return Ok((input, joined));
+            }
+            (input, value)
+        }
+    };
+    Ok((input, value.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Delimiter;
     use anyhow::Result;
 
     #[test]
@@ -200,4 +434,59 @@ mod tests {
             assert!(parser.is_err(), "Should have failed to parse: {:?}", data);
         }
     }
+
+    #[test]
+    fn test_error_kinds() {
+        fn err(data: &str) -> ParseError {
+            Parser::new(data).err().unwrap()
+        }
+
+        assert!(matches!(err(" foo "), ParseError::MissingEquals(p) if p.offset == 5));
+        assert!(matches!(err("foo bar=1"), ParseError::MissingEquals(p) if p.offset == 4));
+        assert!(matches!(err(";foo=bar"), ParseError::InvalidKeyChar(p) if p.offset == 0));
+        assert!(matches!(err("foo.bar=1"), ParseError::InvalidKeyChar(p) if p.offset == 3));
+        assert!(matches!(err("a=1 =2"), ParseError::EmptyKey(p) if p.offset == 4));
+        assert!(
+            matches!(err("a=1\nq=\"foo"), ParseError::UnterminatedQuote(p) if p.line == 2 && p.column == 3)
+        );
+        assert!(matches!(err("q=\"foo\\"), ParseError::DanglingEscape(p) if p.offset == 6));
+    }
+
+    #[test]
+    fn test_lenient_skips_comments_and_delimiters() {
+        let (parser, errors) = Parser::new_lenient("bad # bad=1\none=1");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(parser.len(), 1);
+
+        // after a bad pair, parsing picks up at the next delimiter
+        let commas = ParserConfig::new().delimiter(Delimiter::Char(','));
+        let (parser, errors) = Parser::with_config_lenient("a=1,b,c=3", &commas);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(parser.get("c"), Some("3"));
+    }
+
+    #[test]
+    fn test_std_traits() {
+        let parser = Parser::new("one=1 two=2").unwrap();
+
+        assert_eq!(parser.clone(), parser);
+        assert_eq!(&parser["one"], "1");
+        let mut keys = parser.keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, ["one", "two"]);
+
+        let mut collected: Parser = (&parser)
+            .into_iter()
+            .map(|(k, v)| (k, v.to_string()))
+            .collect();
+        assert_eq!(collected, parser);
+        collected.extend([("two", "3".to_string())]);
+        assert_eq!(collected.get("two"), Some("3"));
+
+        let total: usize = parser
+            .into_iter()
+            .map(|(_, v)| v.parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(total, 3);
+    }
 }
@@ -0,0 +1,1066 @@
+//! Pieces of the grammar that every parsing strategy shares.
+//!
+//! The strategies differ in how they hold on to values, but a key is a key everywhere.  The
+//! pieces that a [`ParserConfig`] changes take it as an argument.
+use nom::bytes::complete::{tag, take_while};
+use std::collections::{hash_map::Entry, HashMap};
+
+use crate::config::{Delimiter, DuplicatePolicy, ParserConfig, Quote};
+use crate::error::{expect, fail, NomResult, ParseError};
+
+/// Returns true if the character may appear in a key: a letter, a digit, or one of
+/// [`ParserConfig::key_chars`]
+pub(crate) fn is_key_char(c: char, config: &ParserConfig) -> bool {
+    let alphanumeric = if config.ascii_keys {
+        c.is_ascii_alphanumeric()
+    } else {
+        c.is_alphanumeric()
+    };
+    alphanumeric || config.key_chars.contains(&c)
+}
+
+/// Skips whitespace, and the comments that [`ParserConfig::comments`] allows.  Returns the
+/// input starting at the next pair.
+pub(crate) fn skip_space<'a>(input: &'a str, config: &ParserConfig) -> &'a str {
+    let mut input = skip_whitespace(input, config);
+    while let Some(comment) = comment(input, config) {
+        input = skip_whitespace(&input[comment.len()..], config);
+    }
+    input
+}
+
+/// Skips whitespace, and line continuations
+fn skip_whitespace<'a>(input: &'a str, config: &ParserConfig) -> &'a str {
+    let mut input = input.trim_start();
+    while let Some(rest) = continuation(input, config) {
+        input = rest.trim_start();
+    }
+    input
+}
+
+/// Skips spaces and tabs, and line continuations, without leaving the line
+fn skip_line_space<'a>(input: &'a str, config: &ParserConfig) -> &'a str {
+    let mut input = input.trim_start_matches([' ', '\t']);
+    while let Some(rest) = continuation(input, config) {
+        input = rest;
+    }
+    input
+}
+
+/// Parses a line continuation under [`ParserConfig::line_continuation`]: a backslash at the
+/// end of a line.  Returns the input at the next line, after the spaces that start it.
+pub(crate) fn continuation<'a>(input: &'a str, config: &ParserConfig) -> Option<&'a str> {
+    if !config.line_continuation {
+        return None;
+    }
+    let rest = input.strip_prefix('\\')?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+    Some(rest.trim_start_matches([' ', '\t']))
+}
+
+/// Joins the lines of a bare value that were continued with a backslash.  Returns `None` if
+/// there are none, so that the value can stay a slice of the input.
+///
+/// A bare value only holds a line break if it is continued, see [`unquoted_value`].
+pub(crate) fn join_lines(value: &str) -> Option<String> {
+    if !value.contains('\n') {
+        return None;
+    }
+    let mut joined = String::with_capacity(value.len());
+    for (i, line) in value.split_inclusive('\n').enumerate() {
+        let line = if i == 0 {
+            line
+        } else {
+            line.trim_start_matches([' ', '\t'])
+        };
+        let line = line
+            .strip_suffix("\\\r\n")
+            .or_else(|| line.strip_suffix("\\\n"))
+            .unwrap_or(line);
+        joined.push_str(line);
+    }
+    Some(joined)
+}
+
+/// Returns the comment at the start of the input, up to the end of its line, if there is one
+pub(crate) fn comment<'a>(input: &'a str, config: &ParserConfig) -> Option<&'a str> {
+    if !config
+        .comments
+        .iter()
+        .any(|c| input.starts_with(c.marker()))
+    {
+        return None;
+    }
+    let end = input.find(['\r', '\n']).unwrap_or(input.len());
+    Some(&input[..end])
+}
+
+/// Parses a key.  The key has to end at whitespace, the separator or the end of the input,
+/// or at the delimiter if it is a flag, and its length in characters has to be within the
+/// configured bounds.
+pub(crate) fn key<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, &'a str> {
+    let (rest, key) = take_while(|c| is_key_char(c, config))(input)?;
+
+    match rest.chars().next() {
+        Some(c) if config.flags && !key.is_empty() && config.delimiter == Delimiter::Char(c) => {}
+        Some(c) if c != config.separator && !c.is_whitespace() => {
+            return fail(rest, ParseError::InvalidKeyChar)
+        }
+        _ => {}
+    }
+    let len = key.chars().count();
+    if len < config.min_key_len {
+        let error = if key.is_empty() {
+            ParseError::EmptyKey
+        } else {
+            ParseError::KeyTooShort
+        };
+        return fail(input, error);
+    }
+    if config.max_key_len.is_some_and(|max| len > max) {
+        return fail(input, ParseError::KeyTooLong);
+    }
+    Ok((rest, key))
+}
+
+/// Parses the separator between a key and its value, `=` unless configured otherwise.
+pub(crate) fn separator<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, &'a str> {
+    match input.strip_prefix(config.separator) {
+        Some(rest) => Ok((rest, &input[..input.len() - rest.len()])),
+        None => fail(input, ParseError::MissingEquals),
+    }
+}
+
+/// Skips the spaces on either side of the separator, if they are allowed.  Under
+/// [`Delimiter::Newline`] they may not run onto another line, unless it continues this one.
+pub(crate) fn separator_space<'a>(input: &'a str, config: &ParserConfig) -> &'a str {
+    if !config.space_around_separator {
+        input
+    } else if config.delimiter == Delimiter::Newline {
+        skip_line_space(input, config)
+    } else {
+        skip_whitespace(input, config)
+    }
+}
+
+/// Parses a key that is not followed by the separator, when [`ParserConfig::flags`] allows
+/// it.  Returns the key, with the input right after it.
+pub(crate) fn flag<'a>(input: &'a str, config: &ParserConfig) -> Option<(&'a str, &'a str)> {
+    if !config.flags {
+        return None;
+    }
+    let (rest, key) = key(input, config).ok()?;
+    match separator(separator_space(rest, config), config) {
+        Ok(_) => None,
+        Err(_) => Some((rest, key)),
+    }
+}
+
+/// Parses a key and the separator after it.  Returns the key, with the input starting at
+/// its value, see [`value_start`].
+pub(crate) fn key_and_separator<'a>(
+    input: &'a str,
+    config: &ParserConfig,
+) -> NomResult<'a, &'a str> {
+    let (input, key) = key(input, config)?;
+    let input = separator_space(input, config);
+    let (input, _) = separator(input, config)?;
+    Ok((value_start(input, config), key))
+}
+
+/// Skips the spaces after a separator.  Returns the input at the value.  If the value is
+/// empty and another pair follows, the input starts right after the separator instead, so
+/// that the value parsers read nothing.
+pub(crate) fn value_start<'a>(input: &'a str, config: &ParserConfig) -> &'a str {
+    let value = separator_space(input, config);
+    let next_pair = config.delimiter == Delimiter::Whitespace && !value.is_empty();
+    if next_pair && is_empty_value(input, value, config) {
+        input
+    } else {
+        value
+    }
+}
+
+/// Returns true if the value after a separator is empty, as in `key=` at the end of the
+/// input, `key=,` under a [`Delimiter::Char`], or `key= next=1`.  `input` starts right after
+/// the separator, `value` after the spaces that may follow it.
+///
+/// Under [`Delimiter::Whitespace`] the spaces after the separator may come before a value,
+/// as in `key= value`, or end an empty one.  The rule is that the value is empty if the
+/// spaces are followed by a comment, or by another key and its separator, so `key= next=1`
+/// is an empty `key` followed by `next`.
+fn is_empty_value(input: &str, value: &str, config: &ParserConfig) -> bool {
+    match value.chars().next() {
+        None => true,
+        Some(c) if c.is_whitespace() => true,
+        Some(c) if config.delimiter == Delimiter::Char(c) => true,
+        Some(_) if config.delimiter == Delimiter::Whitespace && value.len() < input.len() => {
+            comment(value, config).is_some() || key_and_separator(value, config).is_ok()
+        }
+        Some(_) => false,
+    }
+}
+
+/// How a value is written, which decides how it is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueKind {
+    /// In double quotes, with escapes
+    Quoted,
+    /// In triple double quotes, with escapes, and may span lines
+    TripleQuoted,
+    /// In single quotes, taken literally
+    Literal,
+    /// A heredoc, from `<<TAG` up to a line that holds only the tag, taken literally
+    Heredoc,
+    /// Bare, up to the end of the value
+    Unquoted,
+}
+
+/// Looks at the start of a value to tell how it is written.  Quotes that
+/// [`ParserConfig::quotes`] does not allow start an unquoted value, and so does `<<` unless
+/// [`ParserConfig::heredocs`] is set.  `"""` is allowed along with double quotes.
+pub(crate) fn value_kind<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, ValueKind> {
+    let kind = match input.chars().next() {
+        None => ValueKind::Unquoted,
+        Some('"') if config.quotes.contains(&Quote::Double) => {
+            if input.starts_with(TRIPLE_QUOTE) {
+                ValueKind::TripleQuoted
+            } else {
+                ValueKind::Quoted
+            }
+        }
+        Some('\'') if config.quotes.contains(&Quote::Single) => ValueKind::Literal,
+        Some('<') if config.heredocs && is_heredoc(input) => ValueKind::Heredoc,
+        Some(_) => ValueKind::Unquoted,
+    };
+    Ok((input, kind))
+}
+
+const TRIPLE_QUOTE: &str = "\"\"\"";
+
+/// Parses the opening quotes of a value in double quotes, `"` or `"""`.  Returns the
+/// quotes that close the value, with the input right after the opening ones.  A newline right
+/// after `"""` is not part of the value, so that the text can start on the next line.
+pub(crate) fn open_quote(input: &str) -> NomResult<'_, &'static str> {
+    if let Some(rest) = input.strip_prefix(TRIPLE_QUOTE) {
+        let rest = rest
+            .strip_prefix("\r\n")
+            .or_else(|| rest.strip_prefix('\n'))
+            .unwrap_or(rest);
+        return Ok((rest, TRIPLE_QUOTE));
+    }
+    let (rest, _) = tag("\"")(input)?;
+    Ok((rest, "\""))
+}
+
+/// Returns true if the input starts a heredoc, `<<` or `<<~` followed by a tag
+fn is_heredoc(input: &str) -> bool {
+    let Some(rest) = input.strip_prefix("<<") else {
+        return false;
+    };
+    let rest = rest.strip_prefix('~').unwrap_or(rest);
+    rest.starts_with(is_heredoc_tag_char)
+}
+
+/// Returns true if the character may appear in the tag of a heredoc
+fn is_heredoc_tag_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Parses a heredoc: `<<TAG` at the end of a line, then every line up to one that holds
+/// only the tag, which may be indented.  Returns the lines in between, without the newline
+/// that ends the last of them, and the input right after the closing tag.
+///
+/// The text is taken literally.  It also tells if the heredoc was opened with `<<~`, in
+/// which case the indentation its lines share is to be stripped with [`dedent`].
+pub(crate) fn heredoc_value(input: &str) -> NomResult<'_, (&str, bool)> {
+    let start = input;
+    let (rest, _) = tag("<<")(input)?;
+    let (rest, strip_indent) = match rest.strip_prefix('~') {
+        Some(rest) => (rest, true),
+        None => (rest, false),
+    };
+    let (rest, name) = take_while(is_heredoc_tag_char)(rest)?;
+
+    let rest = rest.trim_start_matches([' ', '\t']);
+    let body = match rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))
+    {
+        Some(body) => body,
+        None if rest.is_empty() => return fail(start, ParseError::UnterminatedHeredoc),
+        None => return fail(rest, ParseError::Unexpected),
+    };
+
+    let mut len = 0;
+    for line in body.split_inclusive('\n') {
+        let indented = line.trim_start_matches([' ', '\t']);
+        if indented.trim_end() == name {
+            let text = &body[..len];
+            let text = text
+                .strip_suffix("\r\n")
+                .or_else(|| text.strip_suffix('\n'))
+                .unwrap_or(text);
+            let after_tag = len + line.len() - indented.len() + name.len();
+            return Ok((&body[after_tag..], (text, strip_indent)));
+        }
+        len += line.len();
+    }
+    fail(start, ParseError::UnterminatedHeredoc)
+}
+
+/// Strips the indentation that the lines of a heredoc share, counting spaces and tabs alike.
+/// Lines that hold only whitespace do not count.  Returns `None` if there is nothing to strip,
+/// so that the text can stay a slice of the input.
+pub(crate) fn dedent(text: &str) -> Option<String> {
+    let indent_of = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let indent = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(indent_of)
+        .min()
+        .filter(|&indent| indent > 0)?;
+
+    let mut dedented = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        dedented.push_str(&line[indent_of(line).min(indent)..]);
+    }
+    Some(dedented)
+}
+
+/// Parses a bare value.  It ends at whitespace or the delimiter, or at the end of the line
+/// under [`Delimiter::Newline`], without the spaces before it.  It is empty if the input
+/// starts there.
+///
+/// A value that is continued onto the next line runs on there.  It is returned as written,
+/// see [`join_lines`].  A continuation before the value counts as a space, so the value is
+/// empty.
+pub(crate) fn unquoted_value<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, &'a str> {
+    if continuation(input, config).is_some() {
+        return Ok((input, ""));
+    }
+    let mut len = 0;
+    loop {
+        let rest = &input[len..];
+        let (_, part) = match config.delimiter {
+            Delimiter::Whitespace => take_while(|c: char| !c.is_whitespace())(rest)?,
+            Delimiter::Newline => {
+                let (after, line) = take_while(|c: char| c != '\n' && c != '\r')(rest)?;
+                (after, line.trim_end())
+            }
+            Delimiter::Char(d) => take_while(|c: char| c != d && !c.is_whitespace())(rest)?,
+        };
+        len += part.len();
+
+        // a backslash that ends the part may continue the value on the next line
+        let next = part
+            .strip_suffix('\\')
+            .and_then(|_| continuation(&input[len - 1..], config));
+        match next {
+            Some(next) => len = input.len() - next.len(),
+            None => break,
+        }
+    }
+    Ok((&input[len..], &input[..len]))
+}
+
+/// Parses what comes after a value: whitespace, comments and the delimiter.  The delimiter
+/// may be left out after the last pair.  Returns the input starting at the next pair.
+pub(crate) fn delimiter<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, ()> {
+    match config.delimiter {
+        Delimiter::Whitespace => Ok((skip_space(input, config), ())),
+        Delimiter::Newline => {
+            let rest = skip_line_space(input, config);
+            let rest = match comment(rest, config) {
+                Some(comment) => &rest[comment.len()..],
+                None => rest,
+            };
+            if rest.is_empty() || rest.starts_with(['\n', '\r']) {
+                Ok((skip_space(rest, config), ()))
+            } else {
+                fail(rest, ParseError::MissingDelimiter)
+            }
+        }
+        Delimiter::Char(d) => {
+            let rest = skip_space(input, config);
+            match rest.strip_prefix(d) {
+                Some(rest) => Ok((skip_space(rest, config), ())),
+                None if rest.is_empty() => Ok((rest, ())),
+                None => fail(rest, ParseError::MissingDelimiter),
+            }
+        }
+    }
+}
+
+/// Parses a value in single quotes.  It is taken literally, without decoding escapes, so it
+/// is always a slice of the input.  It may hold anything but a single quote.
+pub(crate) fn literal_value(input: &str) -> NomResult<'_, &str> {
+    let (rest, _) = tag("'")(input)?;
+    let (rest, value) = take_while(|c: char| c != '\'')(rest)?;
+    let (rest, _) = expect(input, ParseError::UnterminatedQuote, tag("'")(rest))?;
+    Ok((rest, value))
+}
+
+/// Decodes the escape sequence at the start of the input, which is a backslash followed by
+/// one of
+///
+/// | escape                   | decodes to                                           |
+/// |--------------------------|------------------------------------------------------|
+/// | `\n`, `\t`, `\r`, `\0`   | newline, tab, carriage return, NUL                   |
+/// | `\\`, `\"`               | a backslash, a quote                                 |
+/// | `\xHH`                   | the ASCII character with that hex code               |
+/// | `\u{X}` to `\u{XXXXXX}` | the Unicode scalar value with that hex code          |
+/// | `\uXXXX`                 | the same, or half of a `\uXXXX\uXXXX` surrogate pair |
+///
+/// Any other character after the backslash is taken literally, unless `strict` is set.
+pub(crate) fn escape(input: &str, strict: bool) -> NomResult<'_, char> {
+    let (rest, _) = tag("\\")(input)?;
+    let mut chars = rest.chars();
+    let Some(c) = chars.next() else {
+        return fail(input, ParseError::DanglingEscape);
+    };
+    let rest = chars.as_str();
+
+    let decoded = match c {
+        'n' => Some((rest, '\n')),
+        't' => Some((rest, '\t')),
+        'r' => Some((rest, '\r')),
+        '0' => Some((rest, '\0')),
+        '\\' | '"' => Some((rest, c)),
+        'x' => hex_digits(rest, 2)
+            .filter(|&(_, code)| code <= 0x7f)
+            .map(|(rest, code)| (rest, char::from(code as u8))),
+        'u' => unicode_escape(rest),
+        _ if strict => None,
+        _ => Some((rest, c)),
+    };
+    match decoded {
+        Some((rest, c)) => Ok((rest, c)),
+        None => fail(input, ParseError::InvalidEscape),
+    }
+}
+
+/// Decodes what follows `\u`, either `{X}` with one to six hex digits, or four hex digits that
+/// may be the first half of a surrogate pair.
+fn unicode_escape(input: &str) -> Option<(&str, char)> {
+    if let Some(braced) = input.strip_prefix('{') {
+        let end = braced.find('}').filter(|end| (1..=6).contains(end))?;
+        let (rest, code) = hex_digits(braced, end)?;
+        return Some((&rest[1..], char::from_u32(code)?));
+    }
+
+    let (rest, high) = hex_digits(input, 4)?;
+    if let Some(c) = char::from_u32(high) {
+        return Some((rest, c));
+    }
+    // a surrogate, which has to be the high half of a pair
+    if !(0xd800..0xdc00).contains(&high) {
+        return None;
+    }
+    let (rest, low) = hex_digits(rest.strip_prefix("\\u")?, 4)?;
+    if !(0xdc00..0xe000).contains(&low) {
+        return None;
+    }
+    let c = char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))?;
+    Some((rest, c))
+}
+
+/// Reads exactly `len` hex digits
+fn hex_digits(input: &str, len: usize) -> Option<(&str, u32)> {
+    let digits = input.get(..len)?;
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((&input[len..], u32::from_str_radix(digits, 16).ok()?))
+}
+
+/// Adds a pair to `map`, following the duplicate policy.  Under [`DuplicatePolicy::Collect`]
+/// the values after the first go into `duplicates`.  Returns false if the key was already
+/// present under [`DuplicatePolicy::Error`].
+pub(crate) fn insert<'a, V>(
+    map: &mut HashMap<&'a str, V>,
+    duplicates: &mut HashMap<&'a str, Vec<V>>,
+    policy: DuplicatePolicy,
+    key: &'a str,
+    value: V,
+) -> bool {
+    match map.entry(key) {
+        Entry::Vacant(e) => {
+            e.insert(value);
+        }
+        Entry::Occupied(e) => match policy {
+            DuplicatePolicy::Error => return false,
+            DuplicatePolicy::FirstWins => {}
+            DuplicatePolicy::LastWins => {
+                // replace the key too, see `replace`
+                e.remove();
+                map.insert(key, value);
+            }
+            DuplicatePolicy::Collect => duplicates.entry(key).or_default().push(value),
+        },
+    }
+    true
+}
+
+/// Inserts a pair into `map`, replacing the key as well as the value of an earlier pair, so
+/// that the key kept in the map is the one that belongs to the value.
+pub(crate) fn replace<'a, V>(map: &mut HashMap<&'a str, V>, key: &'a str, value: V) {
+    map.remove(key);
+    map.insert(key, value);
+}
+
+/// Byte offset of `slice` within `input`.  `slice` has to be a part of `input`.
+pub(crate) fn offset_in(input: &str, slice: &str) -> usize {
+    slice.as_ptr() as usize - input.as_ptr() as usize
+}
+
+/// Skips over a pair that failed to parse, up to the next whitespace or delimiter.  Returns
+/// the input starting at the next pair.
+pub(crate) fn skip_pair<'a>(input: &'a str, config: &ParserConfig) -> &'a str {
+    let end = match config.delimiter {
+        Delimiter::Whitespace => input.find(char::is_whitespace),
+        Delimiter::Newline => input.find(['\n', '\r']),
+        Delimiter::Char(d) => input.find(|c: char| c == d || c.is_whitespace()),
+    };
+    let rest = skip_space(&input[end.unwrap_or(input.len())..], config);
+    match config.delimiter {
+        Delimiter::Char(d) => rest.strip_prefix(d).map_or(rest, |r| skip_space(r, config)),
+        _ => rest,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Comment;
+    use crate::error::to_parse_error;
+    use crate::parser::every_strategy;
+    use crate::{full_copy, KeyValueParser};
+
+    /// Parses with a strategy, and returns what it finds for the keys
+    fn values<'a, P: KeyValueParser<'a>>(
+        input: &'a str,
+        config: &ParserConfig,
+        keys: &[&str],
+    ) -> Vec<String> {
+        let parser = P::with_config(input, config).unwrap();
+        keys.iter()
+            .map(|key| parser.get(key).unwrap().to_string())
+            .collect()
+    }
+
+    /// Parses with a strategy, and returns the error if there is one
+    fn error<'a, P: KeyValueParser<'a>>(
+        input: &'a str,
+        config: &ParserConfig,
+    ) -> Option<ParseError> {
+        P::with_config(input, config).err()
+    }
+
+    #[test]
+    fn test_key() {
+        let config = ParserConfig::default();
+        let key = |input| key(input, &config);
+        assert_eq!(key("some-key_1=value").unwrap(), ("=value", "some-key_1"));
+        assert_eq!(key("key value").unwrap(), (" value", "key"));
+        assert_eq!(key("key").unwrap(), ("", "key"));
+        assert!(key("=value").is_err());
+        assert!(key("k.ey=value").is_err());
+        assert!(key(";key=value").is_err());
+        assert!(key("key:value").is_err());
+
+        let config = ParserConfig::new().separator(':');
+        assert_eq!(super::key("key:value", &config).unwrap(), (":value", "key"));
+        assert!(super::key("key=value", &config).is_err());
+    }
+
+    #[test]
+    fn test_key_chars() {
+        let config = ParserConfig::new().key_chars(&['.', '/', ':']);
+        let key = |input| key(input, &config).map(|(_, key)| key);
+        assert_eq!(key("http.status=200").unwrap(), "http.status");
+        assert_eq!(key("k8s/app=web").unwrap(), "k8s/app");
+        assert_eq!(key("user:id=7").unwrap(), "user:id");
+        assert!(key("some-key=1").is_err());
+
+        assert!(is_key_char('é', &ParserConfig::default()));
+        assert!(!is_key_char('é', &ParserConfig::new().ascii_keys(true)));
+    }
+
+    #[test]
+    fn test_key_len() {
+        let config = ParserConfig::new().min_key_len(2).max_key_len(4);
+        let error = |input| key(input, &config).map_err(|e| to_parse_error(input, e));
+        assert_eq!(key("ab=1", &config).unwrap(), ("=1", "ab"));
+        assert_eq!(key("abcd=1", &config).unwrap(), ("=1", "abcd"));
+        assert_eq!(key("ééé=1", &config).unwrap(), ("=1", "ééé"));
+        assert!(matches!(error("a=1"), Err(ParseError::KeyTooShort(p)) if p.offset == 0));
+        assert!(matches!(error("abcde=1"), Err(ParseError::KeyTooLong(_))));
+        assert!(matches!(error("=1"), Err(ParseError::EmptyKey(_))));
+
+        let config = ParserConfig::new().min_key_len(0);
+        assert_eq!(key("=1", &config).unwrap(), ("=1", ""));
+    }
+
+    #[test]
+    fn test_unquoted_value() {
+        let value = |input, delimiter| {
+            let config = ParserConfig::new().delimiter(delimiter);
+            unquoted_value(input, &config).map(|(rest, value)| (value, rest))
+        };
+        assert_eq!(value("a b", Delimiter::Whitespace).unwrap(), ("a", " b"));
+        assert_eq!(
+            value("a b \nc", Delimiter::Newline).unwrap(),
+            ("a b", " \nc")
+        );
+        assert_eq!(value("a,b", Delimiter::Char(',')).unwrap(), ("a", ",b"));
+        assert_eq!(value("a b", Delimiter::Char(',')).unwrap(), ("a", " b"));
+        assert_eq!(value(",b", Delimiter::Char(',')).unwrap(), ("", ",b"));
+        assert_eq!(value("\nb", Delimiter::Newline).unwrap(), ("", "\nb"));
+    }
+
+    #[test]
+    fn test_delimiter() {
+        fn next(input: &str, kind: Delimiter) -> Result<&str, ()> {
+            let config = ParserConfig::new().delimiter(kind);
+            delimiter(input, &config)
+                .map(|(rest, _)| rest)
+                .map_err(|_| ())
+        }
+        assert_eq!(next("  b=2", Delimiter::Whitespace), Ok("b=2"));
+        assert_eq!(next(" # c\n b=2", Delimiter::Newline), Ok("b=2"));
+        assert_eq!(next("", Delimiter::Newline), Ok(""));
+        assert_eq!(next(" b=2", Delimiter::Newline), Err(()));
+        assert_eq!(next(" , b=2", Delimiter::Char(',')), Ok("b=2"));
+        assert_eq!(next(" ", Delimiter::Char(',')), Ok(""));
+        assert_eq!(next(" b=2", Delimiter::Char(',')), Err(()));
+    }
+
+    #[test]
+    fn test_value_start() {
+        let value = |input, config: &ParserConfig| {
+            let start = value_start(input, config);
+            unquoted_value(start, config).unwrap().1
+        };
+        let config = ParserConfig::default();
+        assert_eq!(value("", &config), "");
+        assert_eq!(value(" b", &config), "b");
+        assert_eq!(value("  b=1", &config), "");
+        assert_eq!(value_start("  b=1", &config), "  b=1");
+        assert_eq!(value("b=1", &config), "b=1");
+        assert_eq!(value(" # note", &config), "");
+        assert_eq!(value_start(" # note", &config), " # note");
+
+        let tight = ParserConfig::new().space_around_separator(false);
+        assert_eq!(value(" b", &tight), "");
+        assert_eq!(value(" b=1", &tight), "");
+
+        let config = ParserConfig::new().delimiter(Delimiter::Char(','));
+        assert_eq!(value(" , b=1", &config), "");
+        assert_eq!(value(" b=1", &config), "b=1");
+
+        let config = ParserConfig::new().delimiter(Delimiter::Newline);
+        assert_eq!(value("  \nb=1", &config), "");
+        assert_eq!(value(" b=1", &config), "b=1");
+    }
+
+    #[test]
+    fn test_continuation() {
+        let config = ParserConfig::new().line_continuation(true);
+        assert_eq!(continuation("\\\n  \tnext", &config), Some("next"));
+        assert_eq!(continuation("\\\r\nnext", &config), Some("next"));
+        assert_eq!(continuation("\\ \nnext", &config), None);
+        assert_eq!(continuation("\\\n", &ParserConfig::default()), None);
+        assert_eq!(skip_space(" \\\n # note\n \\\n\\\n a=1", &config), "a=1");
+
+        let value = |input| unquoted_value(input, &config).unwrap();
+        assert_eq!(value("one\\\n  two three"), (" three", "one\\\n  two"));
+        assert_eq!(value("one\\\n\nb=1"), ("\nb=1", "one\\\n"));
+        assert_eq!(value("one\\ two"), (" two", "one\\"));
+
+        let lines = config.clone().delimiter(Delimiter::Newline);
+        let (rest, value) = unquoted_value("a, \\\r\n  b \\\n c  \nd=1", &lines).unwrap();
+        assert_eq!((rest, value), ("  \nd=1", "a, \\\r\n  b \\\n c"));
+        assert_eq!(join_lines(value).as_deref(), Some("a, b c"));
+        assert_eq!(separator_space(" \\\n  = 1", &lines), "= 1");
+        assert_eq!(delimiter("  \\\n  \nb=1", &lines).unwrap().0, "b=1");
+    }
+
+    #[test]
+    fn test_join_lines() {
+        assert_eq!(join_lines("one\\\n  two").as_deref(), Some("onetwo"));
+        assert_eq!(join_lines("one\\\r\n\ttwo\\\n").as_deref(), Some("onetwo"));
+        assert_eq!(join_lines("one\\"), None);
+        assert_eq!(join_lines("one"), None);
+    }
+
+    #[test]
+    fn test_literal_value() {
+        assert_eq!(
+            literal_value("'say \"hi\" \\n' rest").unwrap(),
+            (" rest", "say \"hi\" \\n")
+        );
+        assert_eq!(literal_value("''").unwrap(), ("", ""));
+        assert!(literal_value("'open").is_err());
+    }
+
+    #[test]
+    fn test_value_kind() {
+        let kind = |input| value_kind(input, &ParserConfig::default()).unwrap().1;
+        assert_eq!(kind("\"a\""), ValueKind::Quoted);
+        assert_eq!(kind("\"\""), ValueKind::Quoted);
+        assert_eq!(kind("\"\"\"a\"\"\""), ValueKind::TripleQuoted);
+        assert_eq!(kind("<<EOF\n"), ValueKind::Unquoted);
+        assert_eq!(kind("<a"), ValueKind::Unquoted);
+
+        let heredocs = ParserConfig::new().heredocs(true);
+        let kind = |input| value_kind(input, &heredocs).unwrap().1;
+        assert_eq!(kind("<<EOF\n"), ValueKind::Heredoc);
+        assert_eq!(kind("<<~EOF\n"), ValueKind::Heredoc);
+        assert_eq!(kind("<<"), ValueKind::Unquoted);
+        assert_eq!(kind("<<~"), ValueKind::Unquoted);
+        assert_eq!(kind("<a"), ValueKind::Unquoted);
+
+        let config = ParserConfig::new().quotes(&[Quote::Single]);
+        let (_, kind) = value_kind("\"\"\"a\"\"\"", &config).unwrap();
+        assert_eq!(kind, ValueKind::Unquoted);
+    }
+
+    #[test]
+    fn test_open_quote() {
+        assert_eq!(open_quote("\"a\"").unwrap(), ("a\"", "\""));
+        assert_eq!(open_quote("\"\"\"a").unwrap(), ("a", "\"\"\""));
+        assert_eq!(open_quote("\"\"\"\na").unwrap(), ("a", "\"\"\""));
+        assert_eq!(open_quote("\"\"\"\r\na").unwrap(), ("a", "\"\"\""));
+        assert_eq!(open_quote("\"\"\"\n\na").unwrap(), ("\na", "\"\"\""));
+    }
+
+    #[test]
+    fn test_heredoc_value() {
+        assert_eq!(
+            heredoc_value("<<EOF\none\ntwo\nEOF \nrest").unwrap(),
+            (" \nrest", ("one\ntwo", false))
+        );
+        assert_eq!(
+            heredoc_value("<<~END  \r\n  one\r\n  END\r\n").unwrap(),
+            ("\r\n", ("  one", true))
+        );
+        assert_eq!(heredoc_value("<<EOF\nEOF").unwrap(), ("", ("", false)));
+        // the tag has to be on a line of its own
+        assert_eq!(
+            heredoc_value("<<EOF\nnot EOF\nEOFS\nEOF").unwrap(),
+            ("", ("not EOF\nEOFS", false))
+        );
+
+        let error = |input| to_parse_error(input, heredoc_value(input).unwrap_err());
+        assert!(
+            matches!(error("<<EOF\ntext"), ParseError::UnterminatedHeredoc(p) if p.offset == 0)
+        );
+        assert!(matches!(error("<<EOF"), ParseError::UnterminatedHeredoc(p) if p.offset == 0));
+        assert!(matches!(error("<<EOF text\nEOF"), ParseError::Unexpected(p) if p.offset == 6));
+    }
+
+    #[test]
+    fn test_dedent() {
+        assert_eq!(
+            dedent("    one\n      two\n\n    three").as_deref(),
+            Some("one\n  two\n\nthree")
+        );
+        assert_eq!(dedent("\tone\n\t\ttwo\n").as_deref(), Some("one\n\ttwo\n"));
+        assert_eq!(dedent("  one\n \n  two").as_deref(), Some("one\n\ntwo"));
+        assert_eq!(dedent("one\n  two"), None);
+        assert_eq!(dedent("   "), None);
+        assert_eq!(dedent(""), None);
+    }
+
+    #[test]
+    fn test_escape() {
+        let decode = |input| escape(input, true).map(|(rest, c)| (c, rest));
+        assert_eq!(decode("\\n rest").unwrap(), ('\n', " rest"));
+        assert_eq!(decode("\\t").unwrap().0, '\t');
+        assert_eq!(decode("\\r").unwrap().0, '\r');
+        assert_eq!(decode("\\0").unwrap().0, '\0');
+        assert_eq!(decode("\\\\").unwrap().0, '\\');
+        assert_eq!(decode("\\\"").unwrap().0, '"');
+        assert_eq!(decode("\\x41").unwrap().0, 'A');
+        assert_eq!(decode("\\u{1F600}x").unwrap(), ('😀', "x"));
+        assert_eq!(decode("\\u00e9").unwrap().0, 'é');
+        assert_eq!(decode("\\ud83d\\ude00x").unwrap(), ('😀', "x"));
+
+        for bad in [
+            "\\",
+            "\\q",
+            "\\x4",
+            "\\xff",
+            "\\u{}",
+            "\\u{1234567}",
+            "\\u{d800}",
+            "\\u12",
+            "\\ud83d",
+            "\\ude00",
+            "\\ud83d\\u0041",
+        ] {
+            assert!(decode(bad).is_err(), "{bad}");
+        }
+
+        // only strict mode rejects unknown escapes
+        assert_eq!(escape("\\q", false).unwrap(), ("", 'q'));
+        assert!(escape("\\x4", false).is_err());
+    }
+
+    #[test]
+    fn test_skip_space() {
+        let config = ParserConfig::default();
+        assert_eq!(
+            skip_space("  # one\n\t# two\r\n a=1 # three", &config),
+            "a=1 # three"
+        );
+        assert_eq!(skip_space("# only a comment", &config), "");
+        assert_eq!(skip_space("; other\na=1", &config), "; other\na=1");
+
+        let config = config.comments(&[Comment::Semicolon, Comment::DoubleSlash]);
+        assert_eq!(skip_space("; one\n// two\na=1", &config), "a=1");
+        assert_eq!(skip_space("# one\na=1", &config), "# one\na=1");
+    }
+
+    #[test]
+    fn test_skip_pair() {
+        let config = ParserConfig::default();
+        assert_eq!(skip_pair(";one=1  two=2", &config), "two=2");
+        assert_eq!(skip_pair("one=\"a b\" two=2", &config), "b\" two=2");
+        assert_eq!(skip_pair("one # comment\ntwo=2", &config), "two=2");
+        assert_eq!(skip_pair("one", &config), "");
+    }
+
+    #[test]
+    fn test_every_strategy_skips_comments() {
+        const DATA: &str =
+            "# leading\none=1 # after a pair\n  # indented\ntwo=\"# kept\" three=#kept\n#";
+        fn check<'a, P: KeyValueParser<'a>>(input: &'a str) {
+            let parser = P::new(input).unwrap();
+            assert_eq!(parser.len(), 3);
+            assert_eq!(parser.get("one").unwrap(), "1");
+            assert_eq!(parser.get("two").unwrap(), "# kept");
+            assert_eq!(parser.get("three").unwrap(), "#kept");
+        }
+        every_strategy!(check(DATA));
+
+        // other markers are opt in
+        const OTHERS: &str = "; semicolon\none=1 // slashes\n";
+        let config = ParserConfig::new().comments(&[Comment::Semicolon, Comment::DoubleSlash]);
+        for values in every_strategy!(values(OTHERS, &config, &["one"])) {
+            assert_eq!(values, ["1"]);
+        }
+        for error in every_strategy!(error(OTHERS, &ParserConfig::default())) {
+            assert!(error.is_some());
+        }
+
+        // and `#` can be turned off
+        let config = ParserConfig::new().comments(&[]);
+        for error in every_strategy!(error("# note\none=1", &config)) {
+            assert!(error.is_some());
+        }
+    }
+
+    #[test]
+    fn test_every_strategy_reads_dialects() {
+        let config = ParserConfig::new()
+            .separator(':')
+            .delimiter(Delimiter::Char(','));
+        const DATA: &str = "a: 1,b :\"x, y\" , c:'z',";
+        for values in every_strategy!(values(DATA, &config, &["a", "b", "c"])) {
+            assert_eq!(values, ["1", "x, y", "z"]);
+        }
+
+        let config = ParserConfig::new().delimiter(Delimiter::Newline);
+        const LINES: &str = "# header\ntitle = Hello there  \nquoted=\"a # b\" # note\n\nlast=x";
+        for values in every_strategy!(values(LINES, &config, &["title", "quoted", "last"])) {
+            assert_eq!(values, ["Hello there", "a # b", "x"]);
+        }
+
+        let config = ParserConfig::new()
+            .delimiter(Delimiter::Char('&'))
+            .space_around_separator(false)
+            .quotes(&[]);
+        for values in every_strategy!(values("q=\"rust\"&page=2", &config, &["q", "page"])) {
+            assert_eq!(values, ["\"rust\"", "2"]);
+        }
+    }
+
+    #[test]
+    fn test_every_strategy_reports_dialect_errors() {
+        let is = |input, config: &ParserConfig, expected: fn(&ParseError) -> bool, offset| {
+            for error in every_strategy!(error(input, config)) {
+                let error = error.unwrap();
+                assert!(expected(&error), "{error:?}");
+                assert_eq!(error.position().offset, offset, "{error:?}");
+            }
+        };
+
+        let commas = ParserConfig::new().delimiter(Delimiter::Char(','));
+        is(
+            "a=1 b=2",
+            &commas,
+            |e| matches!(e, ParseError::MissingDelimiter(_)),
+            4,
+        );
+        is(
+            "a=1,,b=2",
+            &commas,
+            |e| matches!(e, ParseError::InvalidKeyChar(_)),
+            4,
+        );
+
+        let lines = ParserConfig::new().delimiter(Delimiter::Newline);
+        is(
+            "a=\"1\" b=2",
+            &lines,
+            |e| matches!(e, ParseError::MissingDelimiter(_)),
+            6,
+        );
+
+        let tight = ParserConfig::new().space_around_separator(false);
+        is(
+            "a = 1",
+            &tight,
+            |e| matches!(e, ParseError::MissingEquals(_)),
+            1,
+        );
+        // the value of `a` is empty, so `1` is a key
+        is(
+            "a= 1",
+            &tight,
+            |e| matches!(e, ParseError::MissingEquals(_)),
+            4,
+        );
+    }
+
+    #[test]
+    fn test_every_strategy_reads_key_chars() {
+        const DATA: &str = "http.status=200 k8s/app=web user:id=7";
+        let config = ParserConfig::new().key_chars(&['.', '/', ':']);
+        for values in every_strategy!(values(
+            DATA,
+            &config,
+            &["http.status", "k8s/app", "user:id"]
+        )) {
+            assert_eq!(values, ["200", "web", "7"]);
+        }
+        for error in every_strategy!(error(DATA, &ParserConfig::default())) {
+            assert!(matches!(error, Some(ParseError::InvalidKeyChar(p)) if p.offset == 4));
+        }
+
+        let config = ParserConfig::new().ascii_keys(true).max_key_len(8);
+        for error in every_strategy!(error("clé=1", &config)) {
+            assert!(matches!(error, Some(ParseError::InvalidKeyChar(p)) if p.offset == 2));
+        }
+        for error in every_strategy!(error("a=1 very_long_key=2", &config)) {
+            assert!(matches!(error, Some(ParseError::KeyTooLong(p)) if p.offset == 4));
+        }
+
+        let config = ParserConfig::new().min_key_len(0);
+        for values in every_strategy!(values("=1 a=2", &config, &[""])) {
+            assert_eq!(values, ["1"]);
+        }
+        for error in every_strategy!(error("=1", &ParserConfig::default())) {
+            assert!(matches!(error, Some(ParseError::EmptyKey(p)) if p.offset == 0));
+        }
+    }
+
+    #[test]
+    fn test_every_strategy_reads_flags() {
+        const DATA: &str = "quiet ro debug=1 root = /dev/sda1 quiet";
+        let config = ParserConfig::new().flags(true);
+        fn check<'a, P: KeyValueParser<'a>>(config: &ParserConfig) {
+            let parser = P::with_config(DATA, config).unwrap();
+            assert_eq!(parser.len(), 2);
+            assert!(parser.is_flag("quiet"));
+            assert!(parser.is_flag("ro"));
+            assert!(!parser.is_flag("debug"));
+            assert!(!parser.is_flag("missing"));
+            assert!(parser.get("ro").is_none());
+            assert_eq!(parser.lookup("ro"), Some(None));
+            assert_eq!(parser.lookup("debug"), Some(Some("1".into())));
+            assert_eq!(parser.lookup("missing"), None);
+            assert_eq!(parser.get("root").unwrap(), "/dev/sda1");
+        }
+        every_strategy!(check(&config));
+
+        // flags follow the delimiter, and are off by default
+        let commas = config.clone().delimiter(Delimiter::Char(','));
+        let parser = full_copy::Parser::with_config("rw,noatime,uid=0", &commas).unwrap();
+        assert!(parser.is_flag("noatime"));
+        assert_eq!(parser.get("uid"), Some("0"));
+        for error in every_strategy!(error(DATA, &ParserConfig::default())) {
+            assert!(matches!(error, Some(ParseError::MissingEquals(p)) if p.offset == 6));
+        }
+
+        // a flag and a value for the same key give the value
+        let parser = full_copy::Parser::with_config("debug debug=2", &config).unwrap();
+        assert!(parser.is_flag("debug"));
+        assert_eq!(parser.lookup("debug"), Some(Some("2".into())));
+
+        // the duplicate policy is only about pairs
+        let strict = config.clone().duplicate_policy(DuplicatePolicy::Error);
+        for error in every_strategy!(error("a a b=1", &strict)) {
+            assert_eq!(error, None);
+        }
+    }
+
+    #[test]
+    fn test_every_strategy_joins_continued_lines() {
+        const DATA: &str = "path = /usr/bin:\\\n       /bin\nname = plain\nkey \\\n  = spaced\n";
+        let config = ParserConfig::new()
+            .delimiter(Delimiter::Newline)
+            .line_continuation(true);
+        /// Returns the value of `path`, or the error of a strategy that cannot join it
+        fn check<'a, P: KeyValueParser<'a>>(config: &ParserConfig) -> Result<String, ParseError> {
+            let parser = P::with_config(DATA, config)?;
+            assert_eq!(parser.len(), 3);
+            assert_eq!(parser.get("name").unwrap().as_ref(), "plain");
+            assert_eq!(parser.get("key").unwrap().as_ref(), "spaced");
+
+            // the span covers every line the value was written on
+            let span = parser.get_span("path").unwrap();
+            assert_eq!(&DATA[span.value], "/usr/bin:\\\n       /bin");
+            Ok(parser.get("path").unwrap().to_string())
+        }
+        let [full, full_almost, zero, almost, ordered, multi, lazy] =
+            every_strategy!(check(&config));
+        for joined in [full, full_almost, almost, ordered, multi, lazy] {
+            assert_eq!(joined.unwrap(), "/usr/bin:/bin");
+        }
+        // zero_copy can only hand out slices of the input
+        assert!(matches!(zero, Err(ParseError::ContinuedValue(p)) if p.offset == 7));
+
+        // a value that fits on one line is still a slice
+        let parser = crate::zero_copy::Parser::with_config("name = plain\n", &config).unwrap();
+        assert_eq!(parser.get("name"), Some("plain"));
+
+        // between pairs a continuation is a space
+        let spaces = ParserConfig::new().line_continuation(true);
+        const PAIRS: &str = "a=one \\\n  b=1 \\\n c=2";
+        for values in every_strategy!(values(PAIRS, &spaces, &["a", "b", "c"])) {
+            assert_eq!(values, ["one", "1", "2"]);
+        }
+        let parser = full_copy::Parser::with_config("a=one\\\n  two b=1", &spaces).unwrap();
+        assert_eq!(parser.get("a"), Some("onetwo"));
+        let parser = full_copy::Parser::with_config("a=one\\\n", &spaces).unwrap();
+        assert_eq!(parser.get("a"), Some("one"));
+        // a continuation is a space, so another pair may follow an empty value
+        let parser = full_copy::Parser::with_config("a=\\\nb=1", &spaces).unwrap();
+        assert_eq!(parser.get("a"), Some(""));
+        assert_eq!(parser.get("b"), Some("1"));
+
+        // and it is off by default
+        for values in every_strategy!(values(
+            "a=one\\\ntwo=2",
+            &ParserConfig::default(),
+            &["a", "two"]
+        )) {
+            assert_eq!(values, ["one\\", "2"]);
+        }
+    }
+}
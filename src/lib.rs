@@ -46,7 +46,27 @@
 //! of 100gb would be stored in the hashmap in just a few bytes of data.  This is the zero-copy approach.
 
 pub mod almost_zero_copy;
+pub mod config;
+pub mod cst;
+pub mod de;
+pub mod diagnostic;
+pub mod error;
 pub mod full_almost_zero_copy;
 pub mod full_copy;
+mod grammar;
+pub mod multi_map;
+pub mod ordered;
+pub mod parser;
+pub mod ser;
+pub mod span;
+pub mod tree;
+pub mod values;
+pub mod writer;
 pub mod zero_copy;
-pub mod zero_parse;
\ No newline at end of file
+pub mod zero_parse;
+
+pub use de::from_str;
+pub use error::{ParseError, ValueError};
+pub use parser::KeyValueParser;
+pub use ser::to_string;
+pub use span::Span;
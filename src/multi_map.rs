@@ -0,0 +1,330 @@
+//! multi_map keeps every value of a repeated key, for formats where repeating a key is
+//! meaningful rather than a mistake:
+//!
+//! ```pre
+//! tag=a tag=b console=ttyS0 console=tty0
+//! ```
+//!
+//! Values are stored the same way as in [`full_almost_zero_copy`](crate::full_almost_zero_copy),
+//! borrowed from the input unless they had to be unescaped.
+use std::collections::{HashMap, HashSet};
+use std::ops::Index;
+
+use crate::config::ParserConfig;
+use crate::error::{to_parse_error, ParseError};
+use crate::full_almost_zero_copy::{parse_one_key_value, StringOrStr};
+use crate::{grammar, parser, KeyValueParser, Span};
+
+/// Iterator over every pair of a [`Parser`], see [`Parser::iter`]
+pub type Iter<'s, 'a> = Box<dyn Iterator<Item = (&'a str, &'s str)> + 's>;
+
+/// Owning iterator over every pair of a [`Parser`]
+pub type IntoIter<'a> = Box<dyn Iterator<Item = (&'a str, StringOrStr<'a>)> + 'a>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Parser<'a> {
+    map: HashMap<&'a str, Vec<StringOrStr<'a>>>,
+    flags: HashSet<&'a str>,
+    input: &'a str,
+    config: ParserConfig,
+}
+impl<'a> Parser<'a> {
+    /// Construct a new parser.
+    /// If the parser cannot parse the input, an error will be returned.
+    /// ```
+    /// use key_value_parser::multi_map::Parser;
+    /// const DATA: &str = "tag=a other=x tag=b tag=c";
+    /// let parser = Parser::new(DATA).unwrap();
+    /// assert_eq!(parser.len(), 2);
+    /// assert_eq!(parser.get("tag").unwrap(), "a");
+    /// assert_eq!(parser.get_last("tag").unwrap(), "c");
+    /// assert_eq!(parser.get_all("tag").collect::<Vec<_>>(), ["a", "b", "c"]);
+    /// ```
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        Self::with_config(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration.
+    /// If the parser cannot parse the input, an error will be returned.
+    /// Every value of a repeated key is kept whatever the duplicate policy.
+    pub fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        let (parser, errors) = Self::parse(input, config, false);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(parser),
+        }
+    }
+
+    /// Construct a new parser that skips over pairs it cannot parse.
+    /// See [`full_almost_zero_copy::Parser::new_lenient`](crate::full_almost_zero_copy::Parser::new_lenient).
+    pub fn new_lenient(input: &'a str) -> (Self, Vec<ParseError>) {
+        Self::with_config_lenient(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration, that skips over pairs it cannot
+    /// parse.  See [`Parser::new_lenient`].
+    pub fn with_config_lenient(input: &'a str, config: &ParserConfig) -> (Self, Vec<ParseError>) {
+        Self::parse(input, config, true)
+    }
+
+    /// Parses the input, collecting every error.  Unless `lenient` is set, parsing stops
+    /// at the first error.
+    fn parse(input: &'a str, config: &ParserConfig, lenient: bool) -> (Self, Vec<ParseError>) {
+        let mut map: HashMap<_, Vec<_>> = HashMap::new();
+        let mut flags = HashSet::new();
+        let mut errors = Vec::new();
+
+        let mut head = grammar::skip_space(input, config);
+        while !head.is_empty() {
+            match parse_one_key_value(head, config) {
+                Ok((rest, (key, None))) => {
+                    flags.insert(key);
+                    head = rest;
+                }
+                Ok((rest, (key, Some(value)))) => {
+                    map.entry(key).or_default().push(value);
+
+                    head = rest;
+                }
+                Err(e) => {
+                    errors.push(to_parse_error(input, e));
+                    if !lenient {
+                        break;
+                    }
+
+                    head = grammar::skip_pair(head, config);
+                }
+            }
+        }
+
+        (
+            Self {
+                map,
+                flags,
+                input,
+                config: config.clone(),
+            },
+            errors,
+        )
+    }
+
+    /// Gets the first value of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.map.get(key)?.first().map(AsRef::as_ref)
+    }
+
+    /// Gets the last value of a key
+    pub fn get_last(&self, key: &str) -> Option<&str> {
+        self.map.get(key)?.last().map(AsRef::as_ref)
+    }
+
+    /// Gets every value of a key, in the order they appear in the input
+    pub fn get_all<'s>(&'s self, key: &str) -> impl Iterator<Item = &'s str> {
+        self.map.get(key).into_iter().flatten().map(AsRef::as_ref)
+    }
+
+    /// Iterates over every key value pair.  Keys come in no particular order, but the values
+    /// of each key come in the order they appear in the input.
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        Box::new(
+            self.map
+                .iter()
+                .flat_map(|(k, values)| values.iter().map(move |v| (*k, v.as_str()))),
+        )
+    }
+
+    /// Iterates over the distinct keys, in no particular order
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.map.keys().copied()
+    }
+
+    /// Iterates over every value, grouped by key like [`Parser::iter`]
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.map.values().flatten().map(StringOrStr::as_str)
+    }
+
+    /// Gets where the first pair of a key sits in the input.  Pairs that were not parsed from
+    /// the input, such as those added through [`Extend`], have no span.
+    pub fn get_span(&self, key: &str) -> Option<Span> {
+        let (key, _) = self.map.get_key_value(key)?;
+        Span::of(self.input, key, &self.config)
+    }
+
+    /// Returns true if the key appeared on its own, without a value, under
+    /// [`ParserConfig::flags`](crate::config::ParserConfig::flags)
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.flags.contains(key)
+    }
+
+    /// Iterates over the keys that appeared on their own, in no particular order
+    pub fn flags(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.flags.iter().copied()
+    }
+
+    /// Returns how many distinct keys are available
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if there are no key value pairs
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// Parsers are equal if they hold the same pairs, whatever input they came from
+impl PartialEq for Parser<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map && self.flags == other.flags
+    }
+}
+impl Eq for Parser<'_> {}
+
+impl<'s, 'a> IntoIterator for &'s Parser<'a> {
+    type Item = (&'a str, &'s str);
+    type IntoIter = Iter<'s, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for Parser<'a> {
+    type Item = (&'a str, StringOrStr<'a>);
+    type IntoIter = IntoIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(
+            self.map
+                .into_iter()
+                .flat_map(|(k, values)| values.into_iter().map(move |v| (k, v))),
+        )
+    }
+}
+
+/// Gives the first value of the key.  Panics if the key is not present, like indexing a
+/// `HashMap`.
+impl Index<&str> for Parser<'_> {
+    type Output = str;
+
+    fn index(&self, key: &str) -> &str {
+        self.get(key).expect("key not found")
+    }
+}
+
+/// Every pair is kept, values of a repeated key in the order they are collected in
+impl<'a> FromIterator<(&'a str, StringOrStr<'a>)> for Parser<'a> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, StringOrStr<'a>)>>(iter: I) -> Self {
+        let mut parser = Self::default();
+        parser.extend(iter);
+        parser
+    }
+}
+
+/// Every pair is kept, values of a repeated key are appended after the existing ones
+impl<'a> Extend<(&'a str, StringOrStr<'a>)> for Parser<'a> {
+    fn extend<I: IntoIterator<Item = (&'a str, StringOrStr<'a>)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.map.entry(key).or_default().push(value);
+        }
+    }
+}
+
+/// Seen through [`KeyValueParser`], every key holds its first value.
+impl<'a> KeyValueParser<'a> for Parser<'a> {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
+        Parser::new(input)
+    }
+
+    fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        Parser::with_config(input, config)
+    }
+
+    fn get(&self, key: &str) -> Option<StringOrStr<'_>> {
+        Parser::get(self, key).map(StringOrStr::Str)
+    }
+
+    fn get_span(&self, key: &str) -> Option<Span> {
+        Parser::get_span(self, key)
+    }
+
+    fn is_flag(&self, key: &str) -> bool {
+        Parser::is_flag(self, key)
+    }
+
+    fn len(&self) -> usize {
+        Parser::len(self)
+    }
+
+    fn iter(&self) -> parser::Iter<'_> {
+        Box::new(self.map.iter().filter_map(|(k, values)| {
+            let first = values.first()?;
+            Some((*k, StringOrStr::Str(first.as_ref())))
+        }))
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_keys() {
+        const DATA: &str = "console=ttyS0 quiet=1 console=\"tty 0\" console=\"a \\\"b\\\"\"";
+        let parser = Parser::new(DATA).unwrap();
+
+        assert_eq!(parser.len(), 2);
+        assert_eq!(parser.get("console").unwrap(), "ttyS0");
+        assert_eq!(parser.get_last("console").unwrap(), "a \"b\"");
+        assert_eq!(
+            parser.get_all("console").collect::<Vec<_>>(),
+            ["ttyS0", "tty 0", "a \"b\""]
+        );
+        assert_eq!(parser.get_all("quiet").collect::<Vec<_>>(), ["1"]);
+        assert_eq!(parser.get_all("missing").count(), 0);
+        assert!(parser.get_last("missing").is_none());
+        assert_eq!(parser.iter().count(), 4);
+        assert_eq!(parser.values().count(), 4);
+    }
+
+    #[test]
+    fn test_values_borrow_unless_unescaped() {
+        const DATA: &str = "tag=a tag=\"b\\\"\"";
+        let parser = Parser::new(DATA).unwrap();
+
+        let values = &parser.map["tag"];
+        assert!(matches!(values[0], StringOrStr::Str("a")));
+        assert!(matches!(&values[1], StringOrStr::String(s) if s == "b\""));
+    }
+
+    #[test]
+    fn test_bad_parsing() {
+        assert!(Parser::new("tag=a tag").is_err());
+
+        let (parser, errors) = Parser::new_lenient("tag=a ;tag=b tag=c");
+        assert_eq!(parser.get_all("tag").collect::<Vec<_>>(), ["a", "c"]);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_std_traits() {
+        let parser = Parser::new("tag=a tag=b").unwrap();
+
+        assert_eq!(parser.clone(), parser);
+        assert_eq!(&parser["tag"], "a");
+        assert_eq!(
+            (&parser).into_iter().collect::<Vec<_>>(),
+            [("tag", "a"), ("tag", "b")]
+        );
+
+        let mut collected: Parser = [("tag", "a".into())].into_iter().collect();
+        assert_ne!(collected, parser);
+        collected.extend([("tag", "b".into())]);
+        assert_eq!(collected, parser);
+        assert_eq!(parser.into_iter().count(), 2);
+    }
+}
@@ -0,0 +1,356 @@
+//! ordered keeps the pairs in the order they appear in the input, so that iterating over
+//! them, or writing them back out, does not shuffle them.
+//!
+//! The pairs are stored in a `Vec`, with a `HashMap` from each key to its position so that
+//! lookups stay O(1).  Values are stored the same way as in
+//! [`full_almost_zero_copy`](crate::full_almost_zero_copy).
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::ops::Index;
+use std::{slice, vec};
+
+use crate::config::{DuplicatePolicy, ParserConfig};
+use crate::error::{to_parse_error, ParseError, Position};
+use crate::full_almost_zero_copy::{parse_one_key_value, StringOrStr};
+use crate::{grammar, parser, KeyValueParser, Span};
+
+/// Iterator over the pairs of a [`Parser`], see [`Parser::iter`]
+pub type Iter<'s, 'a> = std::iter::Map<
+    slice::Iter<'s, (&'a str, StringOrStr<'a>)>,
+    fn(&'s (&'a str, StringOrStr<'a>)) -> (&'a str, &'s str),
+>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Parser<'a> {
+    pairs: Vec<(&'a str, StringOrStr<'a>)>,
+    index: HashMap<&'a str, usize>,
+    duplicates: HashMap<&'a str, Vec<StringOrStr<'a>>>,
+    flags: HashSet<&'a str>,
+    input: &'a str,
+    config: ParserConfig,
+}
+impl<'a> Parser<'a> {
+    /// Construct a new parser.
+    /// If the parser cannot parse the input, an error will be returned.
+    /// ```
+    /// use key_value_parser::ordered::Parser;
+    /// const DATA: &str = "zebra=1 apple=2 mango=3";
+    /// let parser = Parser::new(DATA).unwrap();
+    /// assert_eq!(parser.get("apple").unwrap(), "2");
+    /// assert_eq!(parser.keys().collect::<Vec<_>>(), ["zebra", "apple", "mango"]);
+    /// ```
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        Self::with_config(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration.
+    /// If the parser cannot parse the input, an error will be returned.
+    ///
+    /// When a later value replaces an earlier one, the pair keeps the position of the
+    /// first occurrence of its key.
+    pub fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        let (parser, errors) = Self::parse(input, config, false);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(parser),
+        }
+    }
+
+    /// Construct a new parser that skips over pairs it cannot parse.
+    /// See [`full_almost_zero_copy::Parser::new_lenient`](crate::full_almost_zero_copy::Parser::new_lenient).
+    pub fn new_lenient(input: &'a str) -> (Self, Vec<ParseError>) {
+        Self::with_config_lenient(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration, that skips over pairs it cannot
+    /// parse.  See [`Parser::new_lenient`].
+    pub fn with_config_lenient(input: &'a str, config: &ParserConfig) -> (Self, Vec<ParseError>) {
+        Self::parse(input, config, true)
+    }
+
+    /// Parses the input, collecting every error.  Unless `lenient` is set, parsing stops
+    /// at the first error.
+    fn parse(input: &'a str, config: &ParserConfig, lenient: bool) -> (Self, Vec<ParseError>) {
+        let mut parser = Self {
+            input,
+            config: config.clone(),
+            ..Self::default()
+        };
+        let mut errors = Vec::new();
+
+        let mut head = grammar::skip_space(input, config);
+        while !head.is_empty() {
+            match parse_one_key_value(head, config) {
+                Ok((rest, (key, None))) => {
+                    parser.flags.insert(key);
+                    head = rest;
+                }
+                Ok((rest, (key, Some(value)))) => {
+                    if !parser.insert(config.duplicate_policy, key, value) {
+                        let offset = grammar::offset_in(input, key);
+                        errors.push(ParseError::DuplicateKey(Position::new(input, offset)));
+                        if !lenient {
+                            break;
+                        }
+                    }
+
+                    head = rest;
+                }
+                Err(e) => {
+                    errors.push(to_parse_error(input, e));
+                    if !lenient {
+                        break;
+                    }
+
+                    head = grammar::skip_pair(head, config);
+                }
+            }
+        }
+
+        (parser, errors)
+    }
+
+    /// Adds a pair, following the duplicate policy.  Returns false if the key was already
+    /// present under [`DuplicatePolicy::Error`].
+    fn insert(&mut self, policy: DuplicatePolicy, key: &'a str, value: StringOrStr<'a>) -> bool {
+        match self.index.entry(key) {
+            Entry::Vacant(e) => {
+                e.insert(self.pairs.len());
+                self.pairs.push((key, value));
+            }
+            Entry::Occupied(e) => match policy {
+                DuplicatePolicy::Error => return false,
+                DuplicatePolicy::FirstWins => {}
+                DuplicatePolicy::LastWins => self.pairs[*e.get()] = (key, value),
+                DuplicatePolicy::Collect => self.duplicates.entry(key).or_default().push(value),
+            },
+        }
+        true
+    }
+
+    /// Gets a value from the container
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let index = *self.index.get(key)?;
+        Some(self.pairs[index].1.as_ref())
+    }
+
+    /// Gets every value of a key, in the order they appear in the input.  Only
+    /// [`DuplicatePolicy::Collect`] keeps more than one value per key.
+    pub fn get_all<'s>(&'s self, key: &str) -> impl Iterator<Item = &'s str> {
+        self.get(key).into_iter().chain(
+            self.duplicates
+                .get(key)
+                .into_iter()
+                .flatten()
+                .map(AsRef::as_ref),
+        )
+    }
+
+    /// Gets the pair at a position, counting in the order the keys appear in the input
+    pub fn get_index(&self, index: usize) -> Option<(&'a str, &str)> {
+        self.pairs.get(index).map(|(k, v)| (*k, v.as_ref()))
+    }
+
+    /// Iterates over the key value pairs in the order they appear in the input
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        self.pairs.iter().map(|(k, v)| (*k, v.as_str()))
+    }
+
+    /// Iterates over the keys in the order they appear in the input
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.pairs.iter().map(|(k, _)| *k)
+    }
+
+    /// Iterates over the values in the order their keys appear in the input
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.pairs.iter().map(|(_, v)| v.as_ref())
+    }
+
+    /// Gets where the pair of a key sits in the input.  Pairs that were not parsed from the
+    /// input, such as those added through [`Extend`], have no span.
+    pub fn get_span(&self, key: &str) -> Option<Span> {
+        let (key, _) = self.pairs[*self.index.get(key)?];
+        Span::of(self.input, key, &self.config)
+    }
+
+    /// Returns true if the key appeared on its own, without a value, under
+    /// [`ParserConfig::flags`](crate::config::ParserConfig::flags)
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.flags.contains(key)
+    }
+
+    /// Iterates over the keys that appeared on their own, in no particular order
+    pub fn flags(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.flags.iter().copied()
+    }
+
+    /// Returns how many key value pairs are available
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns true if there are no key value pairs
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+/// Parsers are equal if they hold the same pairs in the same order, whatever input they
+/// came from
+impl PartialEq for Parser<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pairs == other.pairs
+            && self.duplicates == other.duplicates
+            && self.flags == other.flags
+    }
+}
+impl Eq for Parser<'_> {}
+
+impl<'s, 'a> IntoIterator for &'s Parser<'a> {
+    type Item = (&'a str, &'s str);
+    type IntoIter = Iter<'s, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The pairs come in the order they appear in the input.
+impl<'a> IntoIterator for Parser<'a> {
+    type Item = (&'a str, StringOrStr<'a>);
+    type IntoIter = vec::IntoIter<(&'a str, StringOrStr<'a>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.into_iter()
+    }
+}
+
+/// Panics if the key is not present, like indexing a `HashMap`
+impl Index<&str> for Parser<'_> {
+    type Output = str;
+
+    fn index(&self, key: &str) -> &str {
+        self.get(key).expect("key not found")
+    }
+}
+
+/// Pairs keep the order they are collected in.  A repeated key replaces the earlier value,
+/// keeping its position.
+impl<'a> FromIterator<(&'a str, StringOrStr<'a>)> for Parser<'a> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, StringOrStr<'a>)>>(iter: I) -> Self {
+        let mut parser = Self::default();
+        parser.extend(iter);
+        parser
+    }
+}
+
+/// New keys are appended.  A repeated key replaces the earlier value, keeping its position.
+impl<'a> Extend<(&'a str, StringOrStr<'a>)> for Parser<'a> {
+    fn extend<I: IntoIterator<Item = (&'a str, StringOrStr<'a>)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(DuplicatePolicy::LastWins, key, value);
+        }
+    }
+}
+
+impl<'a> KeyValueParser<'a> for Parser<'a> {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
+        Parser::new(input)
+    }
+
+    fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        Parser::with_config(input, config)
+    }
+
+    fn get(&self, key: &str) -> Option<StringOrStr<'_>> {
+        Parser::get(self, key).map(StringOrStr::Str)
+    }
+
+    fn get_span(&self, key: &str) -> Option<Span> {
+        Parser::get_span(self, key)
+    }
+
+    fn is_flag(&self, key: &str) -> bool {
+        Parser::is_flag(self, key)
+    }
+
+    fn len(&self) -> usize {
+        Parser::len(self)
+    }
+
+    /// The pairs come in the order they appear in the input.
+    fn iter(&self) -> parser::Iter<'_> {
+        Box::new(Parser::iter(self).map(|(k, v)| (k, StringOrStr::Str(v))))
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_source_order() {
+        let keys = (0..100)
+            .rev()
+            .map(|i| format!("key{i}"))
+            .collect::<Vec<_>>();
+        let data = keys
+            .iter()
+            .map(|k| format!("{k}=\"value of {k}\""))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let parser = Parser::new(&data).unwrap();
+        assert_eq!(parser.len(), 100);
+        assert_eq!(parser.keys().collect::<Vec<_>>(), keys);
+        assert_eq!(parser.get("key42").unwrap(), "value of key42");
+        assert_eq!(parser.get_index(0), Some(("key99", "value of key99")));
+        assert_eq!(parser.values().last(), Some("value of key0"));
+    }
+
+    #[test]
+    fn test_duplicates_keep_their_first_position() {
+        const DATA: &str = "b=1 a=2 b=3 c=4";
+
+        let parser = Parser::new(DATA).unwrap();
+        assert_eq!(
+            parser.iter().collect::<Vec<_>>(),
+            [("b", "3"), ("a", "2"), ("c", "4")]
+        );
+
+        let config = ParserConfig::new().duplicate_policy(DuplicatePolicy::FirstWins);
+        let parser = Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.get("b").unwrap(), "1");
+
+        let config = ParserConfig::new().duplicate_policy(DuplicatePolicy::Collect);
+        let parser = Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.get_all("b").collect::<Vec<_>>(), ["1", "3"]);
+
+        let config = ParserConfig::new().duplicate_policy(DuplicatePolicy::Error);
+        assert!(Parser::with_config(DATA, &config).is_err());
+    }
+
+    #[test]
+    fn test_std_traits() {
+        let parser = Parser::new("b=1 a=2").unwrap();
+
+        assert_eq!(parser.clone(), parser);
+        assert_eq!(&parser["a"], "2");
+        assert_eq!(
+            (&parser).into_iter().collect::<Vec<_>>(),
+            [("b", "1"), ("a", "2")]
+        );
+
+        let mut collected: Parser = [("b", "1".into()), ("a", "2".into())].into_iter().collect();
+        assert_eq!(collected, parser);
+        collected.extend([("c", "3".into()), ("b", "4".into())]);
+        assert_eq!(collected.keys().collect::<Vec<_>>(), ["b", "a", "c"]);
+        assert_eq!(&collected["b"], "4");
+
+        let pairs = parser.into_iter().collect::<Vec<_>>();
+        assert_eq!(pairs, [("b", "1".into()), ("a", "2".into())]);
+    }
+}
@@ -0,0 +1,327 @@
+//! The common interface shared by every parsing strategy in this crate.
+//!
+//! Each strategy makes a different trade-off between copying and borrowing from the input,
+//! but they all answer the same questions.  Code that is generic over [`KeyValueParser`] can
+//! switch between strategies without touching its call sites.
+//!
+//! ```
+//! use key_value_parser::KeyValueParser;
+//!
+//! fn port<'a, P: KeyValueParser<'a>>(input: &'a str) -> Option<String> {
+//!     let parser = P::new(input).ok()?;
+//!     parser.get("port").map(|v| v.as_ref().to_string())
+//! }
+//!
+//! const DATA: &str = "host=localhost port=8080";
+//! assert_eq!(port::<key_value_parser::full_copy::Parser>(DATA).unwrap(), "8080");
+//! assert_eq!(port::<key_value_parser::zero_parse::Parser>(DATA).unwrap(), "8080");
+//! ```
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::ParserConfig;
+use crate::error::{interpret, parse_as, ValueError};
+use crate::full_almost_zero_copy::StringOrStr;
+use crate::{values, ParseError, Span};
+
+/// Iterator over the key value pairs of a [`KeyValueParser`].
+pub type Iter<'p> = Box<dyn Iterator<Item = (&'p str, StringOrStr<'p>)> + 'p>;
+
+pub trait KeyValueParser<'a>: Sized {
+    /// Parse the input.  If the input cannot be parsed, an error will be returned.
+    fn new(input: &'a str) -> Result<Self, ParseError>;
+
+    /// Parse the input with the given configuration, see [`ParserConfig`].
+    fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError>;
+
+    /// Gets a value from the container.  Strategies that hold on to owned strings hand out
+    /// borrowed values, strategies that decode lazily may hand out owned ones.
+    fn get(&self, key: &str) -> Option<StringOrStr<'_>>;
+
+    /// Gets where the pair of a key sits in the input, to point at it in error messages
+    fn get_span(&self, key: &str) -> Option<Span>;
+
+    /// Returns true if the key appeared on its own, without a value, under
+    /// [`ParserConfig::flags`]
+    fn is_flag(&self, key: &str) -> bool;
+
+    /// Gets a value, telling a flag apart from a missing key.  Returns `Some(Some(value))`
+    /// for a value, `Some(None)` for a flag and `None` if the key is not there.  A key that
+    /// appeared both on its own and with a value gives the value.
+    fn lookup(&self, key: &str) -> Option<Option<StringOrStr<'_>>> {
+        match self.get(key) {
+            Some(value) => Some(Some(value)),
+            None if self.is_flag(key) => Some(None),
+            None => None,
+        }
+    }
+
+    /// Returns how many key value pairs are available
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no key value pairs
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over every key value pair.  The order is unspecified, unless the strategy
+    /// says otherwise.
+    fn iter(&self) -> Iter<'_>;
+
+    /// Returns true if the key is present
+    fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Gets a value parsed as a `T`, or `None` if the key is not present.  The value is
+    /// parsed straight from the text the parser holds.
+    /// ```
+    /// use key_value_parser::{full_copy::Parser, KeyValueParser};
+    /// let parser = Parser::new("port=8080 host=localhost").unwrap();
+    /// assert_eq!(parser.get_as::<u16>("port").unwrap(), Some(8080));
+    /// assert_eq!(parser.get_as::<u16>("missing").unwrap(), None);
+    /// assert_eq!(
+    ///     parser.get_as::<u16>("host").unwrap_err().to_string(),
+    ///     "invalid value `localhost` for `host`, expected u16: invalid digit found in string"
+    /// );
+    /// ```
+    fn get_as<T>(&self, key: &str) -> Result<Option<T>, ValueError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get(key).map(|v| parse_as(key, &v)).transpose()
+    }
+
+    /// Gets a value parsed as a `T`.  A missing key is an error.
+    fn require_as<T>(&self, key: &str) -> Result<T, ValueError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get_as(key)?.ok_or_else(|| ValueError::Missing {
+            key: key.to_string(),
+        })
+    }
+
+    /// Gets a value parsed as a `T`, or `default` if the key is not present.  A value that
+    /// does not parse is still an error.
+    fn get_or<T>(&self, key: &str, default: T) -> Result<T, ValueError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        Ok(self.get_as(key)?.unwrap_or(default))
+    }
+
+    /// Gets a value interpreted as a boolean, see [`values::parse_bool`]
+    fn get_bool(&self, key: &str) -> Result<Option<bool>, ValueError> {
+        let value = self.get(key);
+        value
+            .map(|v| interpret(key, &v, "a boolean", values::parse_bool))
+            .transpose()
+    }
+
+    /// Gets a value interpreted as a duration, see [`values::parse_duration`]
+    fn get_duration(&self, key: &str) -> Result<Option<Duration>, ValueError> {
+        let value = self.get(key);
+        value
+            .map(|v| interpret(key, &v, "a duration", values::parse_duration))
+            .transpose()
+    }
+
+    /// Gets a value interpreted as a size in bytes, see [`values::parse_bytes_size`]
+    fn get_bytes_size(&self, key: &str) -> Result<Option<u64>, ValueError> {
+        let value = self.get(key);
+        value
+            .map(|v| interpret(key, &v, "a size in bytes", values::parse_bytes_size))
+            .transpose()
+    }
+
+    /// Gets a value interpreted as a percentage, see [`values::parse_percent`]
+    fn get_percent(&self, key: &str) -> Result<Option<f64>, ValueError> {
+        let value = self.get(key);
+        value
+            .map(|v| interpret(key, &v, "a percentage", values::parse_percent))
+            .transpose()
+    }
+}
+
+/// Calls a check, a function that is generic over the [`KeyValueParser`], once for every
+/// strategy.  Returns what each call returned, in the order full_copy, full_almost_zero_copy,
+/// zero_copy, almost_zero_copy, ordered, multi_map and zero_parse.
+#[cfg(test)]
+macro_rules! every_strategy {
+    ($check:ident($($arg:expr),* $(,)?)) => {
+        [
+            $check::<$crate::full_copy::Parser>($($arg),*),
+            $check::<$crate::full_almost_zero_copy::Parser>($($arg),*),
+            $check::<$crate::zero_copy::Parser>($($arg),*),
+            $check::<$crate::almost_zero_copy::Parser>($($arg),*),
+            $check::<$crate::ordered::Parser>($($arg),*),
+            $check::<$crate::multi_map::Parser>($($arg),*),
+            $check::<$crate::zero_parse::Parser>($($arg),*),
+        ]
+    };
+}
+#[cfg(test)]
+pub(crate) use every_strategy;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{full_copy, zero_parse};
+
+    fn check<'a, P: KeyValueParser<'a>>(input: &'a str) {
+        let parser = P::new(input).unwrap();
+
+        assert_eq!(parser.len(), 3);
+        assert!(!parser.is_empty());
+        assert_eq!(parser.get("one").unwrap().as_ref(), "1");
+        assert_eq!(parser.get("quoted").unwrap().as_ref(), "a quoted value");
+        assert!(parser.contains_key("two"));
+        assert!(!parser.contains_key("four"));
+        assert!(parser.get("four").is_none());
+
+        let span = parser.get_span("quoted").unwrap();
+        assert_eq!(&input[span.key], "quoted");
+        assert_eq!(&input[span.value], "\"a quoted value\"");
+        assert_eq!(parser.get_span("two").unwrap().equals, 11..12);
+        assert!(parser.get_span("four").is_none());
+
+        assert_eq!(parser.get_as::<u8>("one").unwrap(), Some(1));
+        assert_eq!(parser.get_or("four", 4u8).unwrap(), 4);
+        assert!(matches!(
+            parser.require_as::<u8>("four"),
+            Err(ValueError::Missing { key }) if key == "four"
+        ));
+        assert!(matches!(
+            parser.get_or("quoted", 0u8),
+            Err(ValueError::Invalid { value, type_name: "u8", .. }) if value == "a quoted value"
+        ));
+
+        let mut pairs = parser
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.as_ref().to_string()))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            [("one", "1"), ("quoted", "a quoted value"), ("two", "2")]
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    fn check_empty<'a, P: KeyValueParser<'a>>(input: &'a str) {
+        let parser = P::new(input).unwrap();
+        assert_eq!(parser.len(), 0);
+        assert!(parser.is_empty());
+        assert_eq!(parser.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_all_strategies_agree() {
+        const DATA: &str = " one=1 two = 2 quoted=\"a quoted value\" ";
+        every_strategy!(check(DATA));
+    }
+
+    fn check_empty_values<'a, P: KeyValueParser<'a>>(input: &'a str) {
+        let parser = P::new(input).unwrap();
+        assert_eq!(parser.len(), 6);
+        for key in ["bare", "quoted", "literal", "spaced", "commented", "last"] {
+            assert_eq!(parser.get(key).unwrap().as_ref(), "", "{key}");
+        }
+        assert_eq!(parser.get_span("bare").unwrap().value, 5..5);
+    }
+
+    #[test]
+    fn test_all_strategies_read_empty_values() {
+        const DATA: &str = "bare= quoted=\"\" literal='' spaced = \n commented= # note\nlast=";
+        every_strategy!(check_empty_values(DATA));
+
+        assert_eq!(zero_parse::parse(DATA, "last").unwrap().as_ref(), "");
+        assert_eq!(zero_parse::parse(DATA, "bare").unwrap().as_ref(), "");
+        // a value may still follow the spaces after `=`, unless it is another pair
+        let parser = full_copy::Parser::new("a= b").unwrap();
+        assert_eq!(parser.get("a"), Some("b"));
+        let parser = full_copy::Parser::new("a= b=1").unwrap();
+        assert_eq!(parser.get("a"), Some(""));
+        assert_eq!(parser.get("b"), Some("1"));
+        let parser = full_copy::Parser::new("a= b c= d=1").unwrap();
+        assert_eq!(parser.get("a"), Some("b"));
+        assert_eq!(parser.get("c"), Some(""));
+        assert_eq!(parser.get("d"), Some("1"));
+        assert_eq!(
+            zero_parse::parse("msg= level=info", "level")
+                .unwrap()
+                .as_ref(),
+            "info"
+        );
+        assert_eq!(
+            full_copy::Parser::new("a=b=c").unwrap().get("a"),
+            Some("b=c")
+        );
+        // unless spaces are not allowed there, when they end the value
+        let tight = ParserConfig::new().space_around_separator(false);
+        let parser = full_copy::Parser::with_config("a= b=1", &tight).unwrap();
+        assert_eq!(parser.get("a"), Some(""));
+        assert_eq!(parser.get("b"), Some("1"));
+        assert!(matches!(
+            full_copy::Parser::with_config("a= b", &tight),
+            Err(ParseError::MissingEquals(_))
+        ));
+    }
+
+    /// Returns the value of the `<<~` heredoc, which not every strategy dedents
+    fn check_multi_line_values<'a, P: KeyValueParser<'a>>(input: &'a str) -> String {
+        let parser = P::with_config(input, &ParserConfig::new().heredocs(true)).unwrap();
+        assert_eq!(parser.len(), 4);
+        assert_eq!(
+            parser.get("cert").unwrap().as_ref(),
+            "BEGIN\nMIIB \"x\"\nEND"
+        );
+        assert_eq!(parser.get("script").unwrap().as_ref(), "echo 'hi'\nexit 0");
+        assert_eq!(parser.get("after").unwrap().as_ref(), "1");
+
+        let span = parser.get_span("script").unwrap();
+        assert_eq!(&input[span.value], "<<EOF\necho 'hi'\nexit 0\nEOF");
+        parser.get("indented").unwrap().to_string()
+    }
+
+    #[test]
+    fn test_all_strategies_read_multi_line_values() {
+        const DATA: &str =
+            "cert=\"\"\"\nBEGIN\nMIIB \"x\"\nEND\"\"\" script=<<EOF\necho 'hi'\nexit 0\nEOF\n\
+                            after=1 indented=<<~EOF\n  one\n    two\n  EOF";
+        const DEDENTED: &str = "one\n  two";
+        // the slice-only strategies keep the indentation of a `<<~` heredoc
+        const SLICED: &str = "  one\n    two";
+        assert_eq!(
+            every_strategy!(check_multi_line_values(DATA)),
+            [DEDENTED, DEDENTED, SLICED, SLICED, DEDENTED, DEDENTED, DEDENTED]
+        );
+    }
+
+    /// Returns the values of `a` and `b`
+    fn check_bare_angle_brackets<'a, P: KeyValueParser<'a>>(input: &'a str) -> [String; 2] {
+        let parser = P::new(input).unwrap();
+        ["a", "b"].map(|key| parser.get(key).unwrap().to_string())
+    }
+
+    #[test]
+    fn test_all_strategies_read_heredocs_only_when_asked() {
+        for values in every_strategy!(check_bare_angle_brackets("a=<<foo b=1")) {
+            assert_eq!(values, ["<<foo", "1"]);
+        }
+        for values in every_strategy!(check_bare_angle_brackets("b=1 a=<<x")) {
+            assert_eq!(values, ["<<x", "1"]);
+        }
+    }
+
+    #[test]
+    fn test_all_strategies_empty() {
+        const DATA: &str = "   ";
+        every_strategy!(check_empty(DATA));
+    }
+}
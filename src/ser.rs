@@ -0,0 +1,636 @@
+//! ser writes any type that implements serde's `Serialize` as key value text.
+//!
+//! The top level has to be a struct or a map.  Nested structs and maps are flattened, joining
+//! the keys on the way down with a separator, `_` unless another one is set.  The elements of
+//! a sequence are numbered the same way.  A `None` field is left out.  Values are quoted and
+//! escaped by the [`writer`](crate::writer), so the output reads back the same.
+//!
+//! ```
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Request<'a> {
+//!     method: &'a str,
+//!     status: u16,
+//!     client: Client<'a>,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct Client<'a> {
+//!     agent: &'a str,
+//!     tags: Vec<&'a str>,
+//! }
+//!
+//! let request = Request {
+//!     method: "GET",
+//!     status: 200,
+//!     client: Client { agent: "curl \"8.0\"", tags: vec!["a", "b"] },
+//! };
+//! assert_eq!(
+//!     key_value_parser::to_string(&request).unwrap(),
+//!     "method=GET status=200 client_agent=\"curl \\\"8.0\\\"\" client_tags_0=a client_tags_1=b"
+//! );
+//!
+//! let text = key_value_parser::ser::to_string_with_separator(&request, "-").unwrap();
+//! assert!(text.contains(" client-agent="));
+//! ```
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt;
+
+use crate::config::ParserConfig;
+use crate::{grammar, writer};
+
+/// Why a value could not be serialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Only structs and maps have keys to write their values under
+    NotAStructOrMap,
+    /// A field or map key, or a separator to join them with, that cannot be written in a key
+    InvalidKey(String),
+    /// An error raised by a `Serialize` implementation
+    Message(String),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotAStructOrMap => f.write_str("only structs and maps can be serialized"),
+            Error::InvalidKey(key) => write!(f, "`{key}` cannot be written as a key"),
+            Error::Message(msg) => f.write_str(msg),
+        }
+    }
+}
+impl std::error::Error for Error {}
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+/// Serializes a struct or map to key value text, joining nested keys with `_`.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    to_string_with_separator(value, "_")
+}
+
+/// Serializes a struct or map to key value text, joining nested keys with `separator`.
+///
+/// The separator becomes part of the keys, so like them it may only be made of letters,
+/// digits, `-` and `_`.  Any other separator is an [`Error::InvalidKey`].
+pub fn to_string_with_separator<T: Serialize + ?Sized>(
+    value: &T,
+    separator: &str,
+) -> Result<String, Error> {
+    if !separator.chars().all(is_key_char) {
+        return Err(Error::InvalidKey(separator.to_string()));
+    }
+    let mut pairs = Pairs {
+        pairs: Vec::new(),
+        separator,
+    };
+    value.serialize(Serializer {
+        pairs: &mut pairs,
+        key: None,
+    })?;
+
+    Ok(writer::to_string(pairs.pairs))
+}
+
+/// The flattened pairs, as they are written.
+struct Pairs<'s> {
+    pairs: Vec<(String, String)>,
+    separator: &'s str,
+}
+impl Pairs<'_> {
+    /// Joins a key below `parent`
+    fn join(&self, parent: Option<&str>, key: &str) -> Result<String, Error> {
+        if key.is_empty() || !key.chars().all(is_key_char) {
+            return Err(Error::InvalidKey(key.to_string()));
+        }
+        Ok(match parent {
+            Some(parent) => format!("{parent}{}{key}", self.separator),
+            None => key.to_string(),
+        })
+    }
+}
+
+/// Returns true if the character may be part of a key that the parsers read by default
+fn is_key_char(c: char) -> bool {
+    grammar::is_key_char(c, &ParserConfig::default())
+}
+
+/// Serializes one value under `key`.  Without a key, only structs and maps are accepted.
+struct Serializer<'p, 's> {
+    pairs: &'p mut Pairs<'s>,
+    key: Option<String>,
+}
+impl Serializer<'_, '_> {
+    fn push(self, value: impl ToString) -> Result<(), Error> {
+        let key = self.key.ok_or(Error::NotAStructOrMap)?;
+        self.pairs.pairs.push((key, value.to_string()));
+        Ok(())
+    }
+}
+
+impl<'p, 's> ser::Serializer for Serializer<'p, 's> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Seq<'p, 's>;
+    type SerializeTuple = Seq<'p, 's>;
+    type SerializeTupleStruct = Seq<'p, 's>;
+    type SerializeTupleVariant = Seq<'p, 's>;
+    type SerializeMap = Map<'p, 's>;
+    type SerializeStruct = Map<'p, 's>;
+    type SerializeStructVariant = Map<'p, 's>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    /// Bytes are written like a sequence of numbers
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        let mut seq = ser::Serializer::serialize_seq(self, Some(v.len()))?;
+        for byte in v {
+            ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+        }
+        ser::SerializeSeq::end(seq)
+    }
+
+    /// `None` leaves the pair out
+    fn serialize_none(self) -> Result<(), Error> {
+        match self.key {
+            Some(_) => Ok(()),
+            None => Err(Error::NotAStructOrMap),
+        }
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    /// A unit is written as an empty value
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.push("")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.push(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    /// The variant name becomes a part of the key
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let key = self.pairs.join(self.key.as_deref(), variant)?;
+        value.serialize(Serializer {
+            pairs: self.pairs,
+            key: Some(key),
+        })
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Seq<'p, 's>, Error> {
+        let key = self.key.ok_or(Error::NotAStructOrMap)?;
+        Ok(Seq {
+            pairs: self.pairs,
+            key,
+            index: 0,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Seq<'p, 's>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Seq<'p, 's>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Seq<'p, 's>, Error> {
+        let key = self.pairs.join(self.key.as_deref(), variant)?;
+        Ok(Seq {
+            pairs: self.pairs,
+            key,
+            index: 0,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Map<'p, 's>, Error> {
+        Ok(Map {
+            pairs: self.pairs,
+            key: self.key,
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Map<'p, 's>, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Map<'p, 's>, Error> {
+        let key = self.pairs.join(self.key.as_deref(), variant)?;
+        Ok(Map {
+            pairs: self.pairs,
+            key: Some(key),
+            next_key: None,
+        })
+    }
+}
+
+/// Writes the elements of a sequence under their index.
+struct Seq<'p, 's> {
+    pairs: &'p mut Pairs<'s>,
+    key: String,
+    index: usize,
+}
+impl Seq<'_, '_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.pairs.join(Some(&self.key), &self.index.to_string())?;
+        self.index += 1;
+        value.serialize(Serializer {
+            pairs: self.pairs,
+            key: Some(key),
+        })
+    }
+}
+impl ser::SerializeSeq for Seq<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+impl ser::SerializeTuple for Seq<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+impl ser::SerializeTupleStruct for Seq<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+impl ser::SerializeTupleVariant for Seq<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Writes the entries of a map or the fields of a struct under their key.
+struct Map<'p, 's> {
+    pairs: &'p mut Pairs<'s>,
+    key: Option<String>,
+    next_key: Option<String>,
+}
+impl Map<'_, '_> {
+    fn entry<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        let key = self.pairs.join(self.key.as_deref(), key)?;
+        value.serialize(Serializer {
+            pairs: self.pairs,
+            key: Some(key),
+        })
+    }
+}
+impl ser::SerializeMap for Map<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| Error::Message("value serialized before its key".to_string()))?;
+        self.entry(&key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+impl ser::SerializeStruct for Map<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+impl ser::SerializeStructVariant for Map<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Turns a map key into a string.  Strings, chars, numbers, bools and unit variants can be
+/// keys.
+struct KeySerializer;
+
+macro_rules! serialize_key_with_to_string {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<String, Error> {
+                Ok(v.to_string())
+            }
+        )*
+    };
+}
+
+macro_rules! reject_key {
+    ($($method:ident($($arg:ty),*) -> $ret:ty,)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ret, Error> {
+                Err(Error::Message("map keys must be strings, numbers or bools".to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    serialize_key_with_to_string! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    reject_key! {
+        serialize_f32(f32) -> String,
+        serialize_f64(f64) -> String,
+        serialize_bytes(&[u8]) -> String,
+        serialize_none() -> String,
+        serialize_unit() -> String,
+        serialize_unit_struct(&'static str) -> String,
+        serialize_seq(Option<usize>) -> Self::SerializeSeq,
+        serialize_tuple(usize) -> Self::SerializeTuple,
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct,
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant,
+        serialize_map(Option<usize>) -> Self::SerializeMap,
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct,
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant,
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error> {
+        Err(Error::Message(
+            "map keys must be strings, numbers or bools".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::full_almost_zero_copy::Parser;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Record {
+        message: String,
+        empty: String,
+        missing: Option<u8>,
+        present: Option<u8>,
+        shapes: Vec<Shape>,
+        labels: BTreeMap<String, bool>,
+        pair: (i8, char),
+    }
+
+    #[test]
+    fn test_flatten() {
+        let record = Record {
+            message: "a \"quoted\" \\ message".to_string(),
+            empty: String::new(),
+            missing: None,
+            present: Some(1),
+            shapes: vec![Shape::Point, Shape::Circle(0.5), Shape::Rect { w: 2, h: 3 }],
+            labels: BTreeMap::from([("x".to_string(), true), ("y".to_string(), false)]),
+            pair: (-1, 'c'),
+        };
+
+        let text = to_string(&record).unwrap();
+        assert_eq!(
+            text,
+            "message=\"a \\\"quoted\\\" \\\\ message\" empty=\"\" present=1 shapes_0=Point \
+             shapes_1_Circle=0.5 shapes_2_Rect_w=2 shapes_2_Rect_h=3 labels_x=true \
+             labels_y=false pair_0=-1 pair_1=c"
+        );
+
+        // and it reads back
+        let parser = Parser::new(&text).unwrap();
+        assert_eq!(parser.get("message").unwrap(), record.message);
+        assert_eq!(parser.get("empty").unwrap(), "");
+
+        let text = to_string_with_separator(&record, "-").unwrap();
+        assert!(text.contains(" shapes-2-Rect-w=2 "));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(to_string(&1), Err(Error::NotAStructOrMap));
+        assert_eq!(to_string(&vec![1]), Err(Error::NotAStructOrMap));
+        assert_eq!(
+            to_string(&BTreeMap::from([("a key", 1)])),
+            Err(Error::InvalidKey("a key".to_string()))
+        );
+        assert!(to_string(&BTreeMap::from([((1, 2), 1)])).is_err());
+
+        // the separator is part of the keys, so it has to be valid in them too
+        let nested = BTreeMap::from([("a", BTreeMap::from([("b", 1)]))]);
+        assert_eq!(
+            to_string_with_separator(&nested, " x "),
+            Err(Error::InvalidKey(" x ".to_string()))
+        );
+        assert_eq!(
+            to_string_with_separator(&nested, "-"),
+            Ok("a-b=1".to_string())
+        );
+    }
+}
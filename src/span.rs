@@ -0,0 +1,150 @@
+//! Where a pair sits in the input it was parsed from.
+//!
+//! ```
+//! use key_value_parser::full_copy::Parser;
+//!
+//! const DATA: &str = "name=\"a b\" port = 80";
+//! let parser = Parser::new(DATA).unwrap();
+//!
+//! let span = parser.get_span("name").unwrap();
+//! assert_eq!(&DATA[span.value], "\"a b\"");
+//!
+//! let span = parser.get_span("port").unwrap();
+//! assert_eq!(&DATA[span.key.clone()], "port");
+//! assert_eq!(&DATA[span.equals.clone()], "=");
+//! assert_eq!(&DATA[span.pair()], "port = 80");
+//! ```
+use std::ops::Range;
+
+use crate::config::ParserConfig;
+use crate::{grammar, zero_parse};
+
+/// Byte ranges of the parts of a pair within the input.  The value is the raw value, with
+/// its quotes and escapes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub key: Range<usize>,
+    pub equals: Range<usize>,
+    pub value: Range<usize>,
+}
+impl Span {
+    /// The whole pair, from the start of the key to the end of the value
+    pub fn pair(&self) -> Range<usize> {
+        self.key.start..self.value.end
+    }
+
+    /// Finds the span of the pair whose key is `key`, a slice of `input` kept by a parser
+    /// that read it with `config`.  Returns `None` if `key` is not a part of `input`, or is not
+    /// followed by a pair.
+    pub(crate) fn of(input: &str, key: &str, config: &ParserConfig) -> Option<Span> {
+        let base = input.as_ptr() as usize;
+        let start = key.as_ptr() as usize;
+        if start < base || start + key.len() > base + input.len() {
+            return None;
+        }
+        let key_start = start - base;
+        let key_end = key_start + key.len();
+
+        let equals = grammar::separator_space(&input[key_end..], config);
+        let (after_equals, _) = grammar::separator(equals, config).ok()?;
+        let value = grammar::value_start(after_equals, config);
+        let (after_value, _) = zero_parse::eat_value(value, config).ok()?;
+
+        let offset = |slice| grammar::offset_in(input, slice);
+        Some(Span {
+            key: key_start..key_end,
+            equals: offset(equals)..offset(after_equals),
+            value: offset(value)..offset(after_value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_of() {
+        const DATA: &str = "one=1 two =\t\"2 \\\" 2\" three=3";
+        let config = ParserConfig::default();
+
+        let span = Span::of(DATA, &DATA[6..9], &config).unwrap();
+        assert_eq!(span.key, 6..9);
+        assert_eq!(span.equals, 10..11);
+        assert_eq!(&DATA[span.value], "\"2 \\\" 2\"");
+
+        let span = Span::of(DATA, &DATA[21..26], &config).unwrap();
+        assert_eq!(&DATA[span.pair()], "three=3");
+
+        // not a part of the input
+        assert!(Span::of(DATA, "one", &config).is_none());
+        // not a key
+        assert!(Span::of(DATA, &DATA[4..5], &config).is_none());
+    }
+
+    #[test]
+    fn test_of_with_config() {
+        use crate::config::Delimiter;
+
+        const DATA: &str = "one: 1, two:'2'";
+        let config = ParserConfig::new()
+            .separator(':')
+            .delimiter(Delimiter::Char(','));
+        let span = Span::of(DATA, &DATA[8..11], &config).unwrap();
+        assert_eq!(span.equals, 11..12);
+        assert_eq!(&DATA[span.pair()], "two:'2'");
+
+        let parser = crate::ordered::Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.get_span("one").unwrap().value, 5..6);
+    }
+
+    #[test]
+    fn test_span_follows_the_value_that_won() {
+        use crate::config::{DuplicatePolicy, ParserConfig};
+        use crate::{full_copy, ordered, zero_copy, zero_parse};
+
+        const DATA: &str = "key=1 key=2";
+        let last = 6..11;
+        assert_eq!(
+            full_copy::Parser::new(DATA)
+                .unwrap()
+                .get_span("key")
+                .unwrap()
+                .pair(),
+            last
+        );
+        assert_eq!(
+            zero_copy::Parser::new(DATA)
+                .unwrap()
+                .get_span("key")
+                .unwrap()
+                .pair(),
+            last
+        );
+        assert_eq!(
+            ordered::Parser::new(DATA)
+                .unwrap()
+                .get_span("key")
+                .unwrap()
+                .pair(),
+            last
+        );
+        assert_eq!(
+            zero_parse::Parser::new(DATA)
+                .unwrap()
+                .get_span("key")
+                .unwrap()
+                .pair(),
+            last
+        );
+
+        let config = ParserConfig::new().duplicate_policy(DuplicatePolicy::FirstWins);
+        let parser = full_copy::Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(parser.get_span("key").unwrap().pair(), 0..5);
+
+        // a pair that replaced the parsed one was not in the input
+        let mut parser = zero_copy::Parser::new(DATA).unwrap();
+        parser.extend([("key", "3")]);
+        assert!(parser.get_span("key").is_none());
+    }
+}
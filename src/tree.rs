@@ -0,0 +1,394 @@
+//! tree nests pairs whose keys are paths, such as `db.primary.host` or `servers[0]`, into
+//! maps and arrays.
+//!
+//! A path is a list of names separated by `.`, where each name may be followed by one or more
+//! indexes in brackets.  The pairs are parsed with
+//! [`full_almost_zero_copy`](crate::full_almost_zero_copy), so the values at the leaves are
+//! borrowed from the input unless they had to be unescaped.
+//!
+//! ```
+//! use key_value_parser::tree::Tree;
+//!
+//! const DATA: &str = "db.primary.host=a db.primary.port=5432 servers[0]=x servers[1]=y";
+//! let tree = Tree::parse(DATA).unwrap();
+//! assert_eq!(tree.get_path("db.primary.port").unwrap().as_str(), Some("5432"));
+//! assert_eq!(tree.get_path("servers[1]").unwrap().as_str(), Some("y"));
+//!
+//! let primary = tree.get_path("db.primary").unwrap();
+//! let keys: Vec<_> = primary.children().map(|(key, _)| key.to_string()).collect();
+//! assert_eq!(keys, ["host", "port"]);
+//! ```
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::config::ParserConfig;
+use crate::full_almost_zero_copy::{self, StringOrStr};
+use crate::ParseError;
+
+/// The characters that keys need besides letters and digits to be written as paths
+pub const KEY_CHARS: &[char] = &['-', '_', '.', '[', ']'];
+
+/// Why the input could not be made into a tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input is not valid key value text
+    Parse(ParseError),
+    /// A key is not a valid path, such as `a..b`, `a[x]` or `[0]`
+    InvalidPath(String),
+    /// A path is used both for a value and for a map or array, or both for a map and an
+    /// array, as in `a=1 a.b=2`.  Names the path.
+    Conflict(String),
+    /// An array is missing an element, as in `a[0]=1 a[2]=3`.  Names the missing element.
+    MissingIndex(String),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => e.fmt(f),
+            Error::InvalidPath(key) => write!(f, "`{key}` is not a valid path"),
+            Error::Conflict(path) => write!(f, "conflicting definitions of `{path}`"),
+            Error::MissingIndex(path) => write!(f, "`{path}` is missing"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+/// One step of a path: a name in a map, or an index in an array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+impl fmt::Display for Segment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Key(key) => f.write_str(key),
+            Segment::Index(index) => write!(f, "[{index}]"),
+        }
+    }
+}
+
+/// A value, or a subtree of maps and arrays.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node<'a> {
+    Value(StringOrStr<'a>),
+    Map(BTreeMap<&'a str, Node<'a>>),
+    Array(Vec<Node<'a>>),
+}
+impl<'a> Node<'a> {
+    /// The value, if this is a leaf
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::Value(value) => Some(value),
+            Node::Map(_) | Node::Array(_) => None,
+        }
+    }
+
+    /// The entries, if this is a map
+    pub fn as_map(&self) -> Option<&BTreeMap<&'a str, Node<'a>>> {
+        match self {
+            Node::Map(map) => Some(map),
+            Node::Value(_) | Node::Array(_) => None,
+        }
+    }
+
+    /// The elements, if this is an array
+    pub fn as_array(&self) -> Option<&[Node<'a>]> {
+        match self {
+            Node::Array(items) => Some(items),
+            Node::Value(_) | Node::Map(_) => None,
+        }
+    }
+
+    /// Gets a node below this one.  The path is relative, so it starts with an index if this
+    /// is an array, as in `[0].name`.
+    pub fn get_path(&self, path: &str) -> Option<&Node<'a>> {
+        parse_path(path, true)?
+            .iter()
+            .try_fold(self, |node, segment| node.child(*segment))
+    }
+
+    /// Iterates over the nodes right below this one, by name in a map and by index in an
+    /// array.  A leaf has none.
+    pub fn children(&self) -> Box<dyn Iterator<Item = (Segment<'a>, &Node<'a>)> + '_> {
+        match self {
+            Node::Value(_) => Box::new(std::iter::empty()),
+            Node::Map(map) => Box::new(map.iter().map(|(key, node)| (Segment::Key(key), node))),
+            Node::Array(items) => Box::new(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, node)| (Segment::Index(index), node)),
+            ),
+        }
+    }
+
+    fn child(&self, segment: Segment<'_>) -> Option<&Node<'a>> {
+        match (self, segment) {
+            (Node::Map(map), Segment::Key(key)) => map.get(key),
+            (Node::Array(items), Segment::Index(index)) => items.get(index),
+            _ => None,
+        }
+    }
+}
+
+/// The pairs of an input, nested by their paths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tree<'a> {
+    root: Node<'a>,
+}
+impl<'a> Tree<'a> {
+    /// Parses the input, allowing the [`KEY_CHARS`] in keys.  If the input cannot be parsed,
+    /// or its paths do not make a tree, an error will be returned.
+    pub fn parse(input: &'a str) -> Result<Self, Error> {
+        Self::parse_with_config(input, &ParserConfig::new().key_chars(KEY_CHARS))
+    }
+
+    /// Parses the input with the given configuration.  Its key characters have to include `.`,
+    /// `[` and `]` for keys to be written as paths.
+    pub fn parse_with_config(input: &'a str, config: &ParserConfig) -> Result<Self, Error> {
+        Self::try_from(full_almost_zero_copy::Parser::with_config(input, config)?)
+    }
+
+    /// The map at the top of the tree
+    pub fn root(&self) -> &Node<'a> {
+        &self.root
+    }
+
+    /// Gets the node at a path, such as `db.primary.port` or `servers[0]`
+    pub fn get_path(&self, path: &str) -> Option<&Node<'a>> {
+        parse_path(path, false)?
+            .iter()
+            .try_fold(&self.root, |node, segment| node.child(*segment))
+    }
+
+    /// Iterates over the nodes at the top of the tree, by name
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (Segment<'a>, &Node<'a>)> + '_> {
+        self.root.children()
+    }
+}
+impl<'a> TryFrom<full_almost_zero_copy::Parser<'a>> for Tree<'a> {
+    type Error = Error;
+
+    fn try_from(parser: full_almost_zero_copy::Parser<'a>) -> Result<Self, Error> {
+        let mut root = Builder::Map(BTreeMap::new());
+        for (key, value) in parser {
+            let path = parse_path(key, false).ok_or_else(|| Error::InvalidPath(key.to_string()))?;
+            insert(&mut root, &path, value)?;
+        }
+        Ok(Self {
+            root: root.finish(&mut Vec::new())?,
+        })
+    }
+}
+
+/// A node while the tree is being built, before arrays are checked for missing elements
+enum Builder<'a> {
+    /// A node that was just created, and becomes whatever the next segment needs
+    Empty,
+    Value(StringOrStr<'a>),
+    Map(BTreeMap<&'a str, Builder<'a>>),
+    Array(BTreeMap<usize, Builder<'a>>),
+}
+impl<'a> Builder<'a> {
+    fn finish(self, path: &mut Vec<Segment<'a>>) -> Result<Node<'a>, Error> {
+        Ok(match self {
+            Builder::Empty => unreachable!("every node gets a value or a child"),
+            Builder::Value(value) => Node::Value(value),
+            Builder::Map(map) => Node::Map(
+                map.into_iter()
+                    .map(|(key, node)| {
+                        path.push(Segment::Key(key));
+                        let node = node.finish(path)?;
+                        path.pop();
+                        Ok((key, node))
+                    })
+                    .collect::<Result<_, Error>>()?,
+            ),
+            Builder::Array(items) => {
+                let mut nodes = Vec::with_capacity(items.len());
+                for (index, node) in items {
+                    path.push(Segment::Index(nodes.len()));
+                    if index != nodes.len() {
+                        return Err(Error::MissingIndex(path_to_string(path)));
+                    }
+                    nodes.push(node.finish(path)?);
+                    path.pop();
+                }
+                Node::Array(nodes)
+            }
+        })
+    }
+}
+
+/// Puts a value at its path, creating the maps and arrays on the way
+fn insert<'a>(
+    root: &mut Builder<'a>,
+    path: &[Segment<'a>],
+    value: StringOrStr<'a>,
+) -> Result<(), Error> {
+    let mut node = root;
+    for (i, segment) in path.iter().enumerate() {
+        if let Builder::Empty = node {
+            *node = match segment {
+                Segment::Key(_) => Builder::Map(BTreeMap::new()),
+                Segment::Index(_) => Builder::Array(BTreeMap::new()),
+            };
+        }
+        node = match (node, segment) {
+            (Builder::Map(map), Segment::Key(key)) => map.entry(key).or_insert(Builder::Empty),
+            (Builder::Array(items), Segment::Index(index)) => {
+                items.entry(*index).or_insert(Builder::Empty)
+            }
+            _ => return Err(Error::Conflict(path_to_string(&path[..i]))),
+        };
+    }
+    match node {
+        Builder::Empty => *node = Builder::Value(value),
+        _ => return Err(Error::Conflict(path_to_string(path))),
+    }
+    Ok(())
+}
+
+/// Splits a path into its segments.  A relative path may start with an index.
+fn parse_path(path: &str, relative: bool) -> Option<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    for (i, part) in path.split('.').enumerate() {
+        let name_len = part.find('[').unwrap_or(part.len());
+        let (name, mut indexes) = part.split_at(name_len);
+        if name.contains(']') || (name.is_empty() && !(relative && i == 0 && !indexes.is_empty())) {
+            return None;
+        }
+        if !name.is_empty() {
+            segments.push(Segment::Key(name));
+        }
+
+        while !indexes.is_empty() {
+            let (index, rest) = indexes.strip_prefix('[')?.split_once(']')?;
+            if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            segments.push(Segment::Index(index.parse().ok()?));
+            indexes = rest;
+        }
+    }
+    Some(segments)
+}
+
+/// Writes a path the way it appears in keys
+fn path_to_string(path: &[Segment<'_>]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        if matches!(segment, Segment::Key(_)) && !out.is_empty() {
+            out.push('.');
+        }
+        out.push_str(&segment.to_string());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nesting() {
+        const DATA: &str = "db.primary.host=a db.primary.port=5432 db.name=\"main db\" \
+                            servers[1]=y servers[0]=x grid[0][1]=b grid[0][0]=a \
+                            users[0].name=ann users[0].admin=true users[1].name=bob";
+        let tree = Tree::parse(DATA).unwrap();
+
+        assert_eq!(tree.get_path("db.name").unwrap().as_str(), Some("main db"));
+        assert_eq!(tree.get_path("servers[0]").unwrap().as_str(), Some("x"));
+        assert_eq!(tree.get_path("grid[0][1]").unwrap().as_str(), Some("b"));
+        assert_eq!(
+            tree.get_path("users[1].name").unwrap().as_str(),
+            Some("bob")
+        );
+        assert_eq!(
+            tree.get_path("servers").unwrap().as_array().unwrap().len(),
+            2
+        );
+        assert_eq!(tree.get_path("db").unwrap().as_map().unwrap().len(), 2);
+
+        let users = tree.get_path("users").unwrap();
+        assert_eq!(users.get_path("[0].admin").unwrap().as_str(), Some("true"));
+        let paths: Vec<_> = users
+            .children()
+            .map(|(index, user)| format!("{index}:{}", user.children().count()))
+            .collect();
+        assert_eq!(paths, ["[0]:2", "[1]:1"]);
+
+        let top: Vec<_> = tree.iter().map(|(key, _)| key.to_string()).collect();
+        assert_eq!(top, ["db", "grid", "servers", "users"]);
+
+        assert!(tree.get_path("db.missing").is_none());
+        assert!(tree.get_path("servers[2]").is_none());
+        assert!(tree.get_path("servers.0").is_none());
+        assert!(tree.get_path("[0]").is_none());
+        assert!(tree.get_path("db.primary.host.more").is_none());
+    }
+
+    #[test]
+    fn test_leaves_are_borrowed() {
+        const DATA: &str = "a.b=plain a.c=\"esc\\\"aped\"";
+        let tree = Tree::parse(DATA).unwrap();
+        match tree.get_path("a.b").unwrap() {
+            Node::Value(StringOrStr::Str(value)) => {
+                assert_eq!(value.as_ptr(), DATA[4..].as_ptr())
+            }
+            node => panic!("expected a borrowed value, got {node:?}"),
+        }
+        assert!(matches!(
+            tree.get_path("a.c").unwrap(),
+            Node::Value(StringOrStr::String(_))
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |input| Tree::parse(input).unwrap_err();
+        assert_eq!(err("a=1 a.b=2"), Error::Conflict("a".to_string()));
+        assert_eq!(err("a.b=2 a=1"), Error::Conflict("a".to_string()));
+        assert_eq!(err("x.a[0]=1 x.a.b=2"), Error::Conflict("x.a".to_string()));
+        assert_eq!(
+            err("a[0]=1 a[2]=3"),
+            Error::MissingIndex("a[1]".to_string())
+        );
+        assert_eq!(err("a.b[1]=1"), Error::MissingIndex("a.b[0]".to_string()));
+        for path in ["a..b", "a[x]", "[0]", "a[0", "a]b", ".a", "a[]"] {
+            assert!(
+                matches!(
+                    Tree::parse(&format!("{path}=1")),
+                    Err(Error::InvalidPath(_))
+                ),
+                "{path}"
+            );
+        }
+        assert!(matches!(err("a=\"1"), Error::Parse(_)));
+
+        assert_eq!(
+            err("a=1 a.b=2").to_string(),
+            "conflicting definitions of `a`"
+        );
+    }
+
+    #[test]
+    fn test_from_parser() {
+        let config = ParserConfig::new().key_chars(&['.']);
+        let parser = full_almost_zero_copy::Parser::with_config("a.b=1", &config).unwrap();
+        let tree = Tree::try_from(parser).unwrap();
+        assert_eq!(tree.get_path("a.b").unwrap().as_str(), Some("1"));
+
+        // without the extra key characters, paths are not keys
+        assert!(matches!(
+            Tree::parse_with_config("a.b=1", &ParserConfig::default()),
+            Err(Error::Parse(ParseError::InvalidKeyChar(_)))
+        ));
+    }
+}
@@ -0,0 +1,264 @@
+//! Interpreters for values that people write by hand: booleans, durations, byte sizes and
+//! percentages.
+//!
+//! They are also available on every parser through
+//! [`KeyValueParser::get_bool`](crate::KeyValueParser::get_bool) and its siblings.
+//!
+//! ```
+//! use key_value_parser::{full_copy::Parser, KeyValueParser};
+//! use std::time::Duration;
+//!
+//! const DATA: &str = "enabled=yes retry_after=1m30s cache=512MiB ratio=85%";
+//! let parser = Parser::new(DATA).unwrap();
+//! assert_eq!(parser.get_bool("enabled").unwrap(), Some(true));
+//! assert_eq!(parser.get_duration("retry_after").unwrap(), Some(Duration::from_secs(90)));
+//! assert_eq!(parser.get_bytes_size("cache").unwrap(), Some(512 * 1024 * 1024));
+//! assert_eq!(parser.get_percent("ratio").unwrap(), Some(0.85));
+//! ```
+use std::fmt;
+use std::time::Duration;
+
+/// Why a value could not be interpreted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl std::error::Error for Error {}
+
+fn error(reason: impl Into<String>) -> Error {
+    Error(reason.into())
+}
+
+/// Interprets a boolean.  Case does not matter.
+///
+/// | true    | false   |
+/// |---------|---------|
+/// | `true`  | `false` |
+/// | `yes`   | `no`    |
+/// | `on`    | `off`   |
+/// | `y`     | `n`     |
+/// | `1`     | `0`     |
+pub fn parse_bool(value: &str) -> Result<bool, Error> {
+    const TRUE: [&str; 5] = ["true", "yes", "on", "y", "1"];
+    const FALSE: [&str; 5] = ["false", "no", "off", "n", "0"];
+
+    if TRUE.iter().any(|t| value.eq_ignore_ascii_case(t)) {
+        Ok(true)
+    } else if FALSE.iter().any(|f| value.eq_ignore_ascii_case(f)) {
+        Ok(false)
+    } else {
+        Err(error("expected true/false, yes/no, on/off, y/n or 1/0"))
+    }
+}
+
+/// Interprets a duration, written as one or more numbers each followed by a unit, such as
+/// `30s`, `1m30s` or `1.5h`.  A bare `0` is also accepted.
+///
+/// | unit         | meaning      |
+/// |--------------|--------------|
+/// | `ns`         | nanoseconds  |
+/// | `us` or `µs` | microseconds |
+/// | `ms`         | milliseconds |
+/// | `s`          | seconds      |
+/// | `m`          | minutes      |
+/// | `h`          | hours        |
+/// | `d`          | days         |
+pub fn parse_duration(value: &str) -> Result<Duration, Error> {
+    if value == "0" {
+        return Ok(Duration::ZERO);
+    }
+    if value.is_empty() {
+        return Err(error("expected a duration such as `30s`"));
+    }
+
+    let mut nanos: u128 = 0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let (number, after_number) = split_number(rest)?;
+        let unit_len = after_number
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(after_number.len());
+        let (unit, after_unit) = after_number.split_at(unit_len);
+
+        let unit_nanos: u128 = match unit {
+            "ns" => 1,
+            "us" | "µs" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60 * 1_000_000_000,
+            "h" => 60 * 60 * 1_000_000_000,
+            "d" => 24 * 60 * 60 * 1_000_000_000,
+            "" => return Err(error(format!("missing unit after `{number}`"))),
+            _ => return Err(error(format!("unknown unit `{unit}`"))),
+        };
+        nanos = scale(number, unit_nanos)
+            .and_then(|n| nanos.checked_add(n))
+            .ok_or_else(|| error("duration is too long"))?;
+        rest = after_unit;
+    }
+
+    let secs = u64::try_from(nanos / 1_000_000_000).map_err(|_| error("duration is too long"))?;
+    Ok(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+/// Interprets a size in bytes, written as a number followed by an optional unit, such as
+/// `512`, `512MiB` or `1.5 GB`.  Case does not matter, and there may be a space before the
+/// unit.
+///
+/// | unit                | multiplier |
+/// |---------------------|------------|
+/// | none or `b`         | 1          |
+/// | `kb`                | 1000       |
+/// | `mb`, `gb`, `tb`, `pb` | powers of 1000 |
+/// | `k` or `kib`        | 1024       |
+/// | `m`, `g`, `t`, `p` or `mib`, `gib`, `tib`, `pib` | powers of 1024 |
+///
+/// Fractions of a byte are rounded down.
+pub fn parse_bytes_size(value: &str) -> Result<u64, Error> {
+    let (number, unit) = split_number(value)?;
+    let unit = unit.strip_prefix(' ').unwrap_or(unit).to_ascii_lowercase();
+
+    let multiplier: u128 = match unit.as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u128.pow(2),
+        "gb" => 1000u128.pow(3),
+        "tb" => 1000u128.pow(4),
+        "pb" => 1000u128.pow(5),
+        "k" | "kib" => 1024,
+        "m" | "mib" => 1024u128.pow(2),
+        "g" | "gib" => 1024u128.pow(3),
+        "t" | "tib" => 1024u128.pow(4),
+        "p" | "pib" => 1024u128.pow(5),
+        _ => return Err(error(format!("unknown unit `{unit}`"))),
+    };
+    scale(number, multiplier)
+        .and_then(|n| u64::try_from(n).ok())
+        .ok_or_else(|| error("size is too large"))
+}
+
+/// Interprets a percentage as a fraction, so that `85%` is `0.85`.  A number without `%` is
+/// taken to be a fraction already.
+pub fn parse_percent(value: &str) -> Result<f64, Error> {
+    let (number, divisor) = match value.strip_suffix('%') {
+        Some(number) => (number, 100.0),
+        None => (value, 1.0),
+    };
+    let number: f64 = number
+        .trim_end()
+        .parse()
+        .map_err(|_| error("expected a percentage such as `85%`"))?;
+    if !number.is_finite() {
+        return Err(error("expected a percentage such as `85%`"));
+    }
+    Ok(number / divisor)
+}
+
+/// Splits a leading unsigned decimal number off the value
+fn split_number(value: &str) -> Result<(&str, &str), Error> {
+    let end = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let number = &value[..end];
+    if number.is_empty() || number == "." || number.matches('.').count() > 1 {
+        return Err(error(format!("expected a number at `{value}`")));
+    }
+    Ok((number, &value[end..]))
+}
+
+/// Multiplies a decimal number by a whole unit, rounding down.  Returns `None` on overflow.
+fn scale(number: &str, unit: u128) -> Option<u128> {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let whole: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+
+    // digits past the 18th are too small to matter, and would risk overflow
+    let fraction = &fraction[..fraction.len().min(18)];
+    let share = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u128>().ok()? * unit / 10u128.pow(fraction.len() as u32)
+    };
+
+    whole.checked_mul(unit)?.checked_add(share)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bool() {
+        for value in ["true", "Yes", "ON", "y", "1"] {
+            assert_eq!(parse_bool(value), Ok(true));
+        }
+        for value in ["false", "No", "OFF", "n", "0"] {
+            assert_eq!(parse_bool(value), Ok(false));
+        }
+        assert!(parse_bool("maybe").is_err());
+        assert!(parse_bool("").is_err());
+    }
+
+    #[test]
+    fn test_duration() {
+        let secs = Duration::from_secs;
+        assert_eq!(parse_duration("30s"), Ok(secs(30)));
+        assert_eq!(parse_duration("1m30s"), Ok(secs(90)));
+        assert_eq!(parse_duration("1.5h"), Ok(secs(5400)));
+        assert_eq!(parse_duration("2d"), Ok(secs(2 * 86400)));
+        assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1us5ns"), Ok(Duration::from_nanos(1005)));
+        assert_eq!(parse_duration("3µs"), Ok(Duration::from_micros(3)));
+        assert_eq!(parse_duration(".5s"), Ok(Duration::from_millis(500)));
+
+        assert_eq!(
+            parse_duration("30").unwrap_err().to_string(),
+            "missing unit after `30`"
+        );
+        assert_eq!(
+            parse_duration("30x").unwrap_err().to_string(),
+            "unknown unit `x`"
+        );
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("1.2.3s").is_err());
+        assert!(parse_duration("99999999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_bytes_size() {
+        assert_eq!(parse_bytes_size("512"), Ok(512));
+        assert_eq!(parse_bytes_size("512B"), Ok(512));
+        assert_eq!(parse_bytes_size("512MiB"), Ok(512 * 1024 * 1024));
+        assert_eq!(parse_bytes_size("512m"), Ok(512 * 1024 * 1024));
+        assert_eq!(parse_bytes_size("2kb"), Ok(2000));
+        assert_eq!(parse_bytes_size("1.5 GB"), Ok(1_500_000_000));
+        assert_eq!(parse_bytes_size("1.5K"), Ok(1536));
+        assert_eq!(parse_bytes_size("0.5b"), Ok(0));
+
+        assert_eq!(
+            parse_bytes_size("1EiB").unwrap_err().to_string(),
+            "unknown unit `eib`"
+        );
+        assert!(parse_bytes_size("MiB").is_err());
+        assert!(parse_bytes_size("20000000PiB").is_err());
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(parse_percent("85%"), Ok(0.85));
+        assert_eq!(parse_percent("0.85"), Ok(0.85));
+        assert_eq!(parse_percent("150%"), Ok(1.5));
+        assert!(parse_percent("%").is_err());
+        assert!(parse_percent("lots").is_err());
+        assert!(parse_percent("inf%").is_err());
+    }
+}
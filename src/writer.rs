@@ -0,0 +1,237 @@
+//! writer turns key value pairs back into text that the parsers read.
+//!
+//! Values are written bare unless they are empty, start with a single quote or `<<`, or hold
+//! whitespace, double quotes or control characters.
+//!
+//! Those values are quoted.  `"`, `\`, newlines, tabs and other control characters are escaped
+//! the way [`full_almost_zero_copy`](crate::full_almost_zero_copy) unescapes them, so that
+//! parsing the output gives back the same pairs, on a single line.
+//!
+//! ```
+//! use key_value_parser::{full_almost_zero_copy::Parser, writer};
+//!
+//! const DATA: &str = "name=\"a \\\"quoted\\\" name\"";
+//! let parser = Parser::new(DATA).unwrap();
+//! assert_eq!(writer::to_string(&parser), DATA);
+//!
+//! let text = writer::to_string([("empty", ""), ("path", "C:\\temp")]);
+//! assert_eq!(text, "empty=\"\" path=C:\\temp");
+//! ```
+use std::{fmt, io};
+
+/// Displays a value the way it has to be written, quoted and escaped if it has to be.
+pub struct Value<'a>(pub &'a str);
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if needs_quotes(self.0) {
+            write_quoted(f, self.0)
+        } else {
+            f.write_str(self.0)
+        }
+    }
+}
+
+/// Returns true if a value would not read back the same without quotes
+pub(crate) fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || value.starts_with('\'')
+        || value.starts_with("<<")
+        || value.contains(|c: char| c.is_whitespace() || c.is_control() || c == '"')
+}
+
+/// Writes a value in quotes, escaping `"`, `\` and control characters.
+pub(crate) fn write_quoted<W: fmt::Write + ?Sized>(out: &mut W, value: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\t' => out.write_str("\\t")?,
+            '\r' => out.write_str("\\r")?,
+            '\0' => out.write_str("\\0")?,
+            c if c.is_control() => write!(out, "\\u{{{:x}}}", u32::from(c))?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+/// Writes the pairs, separated by a space.  Keys are written as they are, so they have to be
+/// valid keys for the output to parse.  Flags are not pairs, so they are not written, see
+/// [`write_with_flags`].
+pub fn write<W, I, K, V>(out: &mut W, pairs: I) -> fmt::Result
+where
+    W: fmt::Write + ?Sized,
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    write_with_flags(out, pairs, None::<&str>)
+}
+
+/// Writes the pairs, see [`write`], followed by the flags as bare keys.  The output reads
+/// back the same under [`ParserConfig::flags`](crate::config::ParserConfig::flags).
+/// ```
+/// use key_value_parser::{config::ParserConfig, full_copy::Parser, writer};
+///
+/// let config = ParserConfig::new().flags(true);
+/// let parser = Parser::with_config("quiet a=1", &config).unwrap();
+/// let text = writer::to_string_with_flags(&parser, parser.flags());
+/// assert_eq!(text, "a=1 quiet");
+/// assert_eq!(Parser::with_config(&text, &config).unwrap(), parser);
+/// ```
+pub fn write_with_flags<W, I, K, V, F, S>(out: &mut W, pairs: I, flags: F) -> fmt::Result
+where
+    W: fmt::Write + ?Sized,
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+    F: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut first = true;
+    for (key, value) in pairs {
+        if !first {
+            out.write_char(' ')?;
+        }
+        first = false;
+        write!(out, "{}={}", key.as_ref(), Value(value.as_ref()))?;
+    }
+    for flag in flags {
+        if !first {
+            out.write_char(' ')?;
+        }
+        first = false;
+        out.write_str(flag.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Writes the pairs to an [`io::Write`], see [`write`]
+pub fn write_io<W, I, K, V>(out: &mut W, pairs: I) -> io::Result<()>
+where
+    W: io::Write + ?Sized,
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    for (i, (key, value)) in pairs.into_iter().enumerate() {
+        if i > 0 {
+            out.write_all(b" ")?;
+        }
+        write!(out, "{}={}", key.as_ref(), Value(value.as_ref()))?;
+    }
+    Ok(())
+}
+
+/// Writes the pairs to a new `String`, see [`write`]
+pub fn to_string<I, K, V>(pairs: I) -> String
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut out = String::new();
+    write(&mut out, pairs).expect("writing to a String cannot fail");
+    out
+}
+
+/// Writes the pairs and the flags to a new `String`, see [`write_with_flags`]
+pub fn to_string_with_flags<I, K, V, F, S>(pairs: I, flags: F) -> String
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+    F: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut out = String::new();
+    write_with_flags(&mut out, pairs, flags).expect("writing to a String cannot fail");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParserConfig;
+    use crate::{full_almost_zero_copy, full_copy, multi_map, ordered, zero_parse};
+
+    const DATA: &str = "plain=1 empty=\"\" spaced=\"a b\" quotes=\"say \\\"hi\\\"\" \
+                        slash=a\\b quoted_slash=\"a \\\\ b\" tab=\"\t\" line=\"\n\"";
+
+    #[test]
+    fn test_value() {
+        assert_eq!(Value("plain").to_string(), "plain");
+        assert_eq!(Value("").to_string(), "\"\"");
+        assert_eq!(Value("a b").to_string(), "\"a b\"");
+        assert_eq!(Value("a\"b").to_string(), "\"a\\\"b\"");
+        assert_eq!(Value("a\\b").to_string(), "a\\b");
+        assert_eq!(Value("'a'").to_string(), "\"'a'\"");
+        assert_eq!(Value("it's").to_string(), "it's");
+        assert_eq!(Value("<<EOF").to_string(), "\"<<EOF\"");
+        assert_eq!(Value("a<<b").to_string(), "a<<b");
+        assert_eq!(Value("a\\ \"").to_string(), "\"a\\\\ \\\"\"");
+        assert_eq!(Value("a\nb\tc").to_string(), "\"a\\nb\\tc\"");
+        assert_eq!(Value("\0\r\u{1b}").to_string(), "\"\\0\\r\\u{1b}\"");
+    }
+
+    #[test]
+    fn test_multi_line_round_trip() {
+        let message = "first line\n\tsecond \"line\"\r\n\u{7f}é";
+        let text = to_string([("message", message)]);
+        assert!(!text.contains('\n'));
+
+        let parser = full_almost_zero_copy::Parser::new(&text).unwrap();
+        assert_eq!(parser.get("message").unwrap(), message);
+        let parser = full_copy::Parser::new(&text).unwrap();
+        assert_eq!(parser.get("message").unwrap(), message);
+        assert_eq!(zero_parse::parse(&text, "message").unwrap(), message);
+    }
+
+    #[test]
+    fn test_io() {
+        let mut out = Vec::new();
+        write_io(&mut out, [("a", "1"), ("b", "two words")]).unwrap();
+        assert_eq!(out, b"a=1 b=\"two words\"");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let parser = full_copy::Parser::new(DATA).unwrap();
+        let text = to_string(&parser);
+        assert_eq!(full_copy::Parser::new(&text).unwrap(), parser);
+
+        let parser = full_almost_zero_copy::Parser::new(DATA).unwrap();
+        let text = to_string(&parser);
+        assert_eq!(full_almost_zero_copy::Parser::new(&text).unwrap(), parser);
+
+        let parser = ordered::Parser::new(DATA).unwrap();
+        let text = to_string(&parser);
+        assert_eq!(ordered::Parser::new(&text).unwrap(), parser);
+
+        let parser = multi_map::Parser::new("tag=\"a b\" tag=c").unwrap();
+        let text = to_string(&parser);
+        assert_eq!(multi_map::Parser::new(&text).unwrap(), parser);
+
+        let parser = zero_parse::Parser::new(DATA).unwrap();
+        let text = to_string(&parser);
+        assert_eq!(zero_parse::Parser::new(&text).unwrap(), parser);
+    }
+
+    #[test]
+    fn test_flags_round_trip() {
+        let config = ParserConfig::new().flags(true);
+        let parser = full_copy::Parser::with_config("quiet a=1 ro", &config).unwrap();
+        assert_eq!(to_string(&parser), "a=1");
+
+        let text = to_string_with_flags(&parser, parser.flags());
+        assert_eq!(
+            full_copy::Parser::with_config(&text, &config).unwrap(),
+            parser
+        );
+
+        let parser = ordered::Parser::with_config("quiet", &config).unwrap();
+        assert_eq!(to_string_with_flags(&parser, parser.flags()), "quiet");
+    }
+}
@@ -4,27 +4,39 @@
 //! one=1 two=2 three=three quoted="this is a quoted value" escaped="this is a value with \"escaped\" quotes"
 //! ```
 //!
-use anyhow::Result;
-use nom::{bytes::complete::{take_while, take, tag}, character::complete::multispace0, IResult, Finish};
+use nom::bytes::complete::{take, take_while};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
-use crate::full_almost_zero_copy::{StringOrStr, parse_value};
+use crate::config::{DuplicatePolicy, ParserConfig};
+use crate::error::{
+    expect, fail, parse_as as parse_as_type, to_parse_error, NomError, NomResult, ParseError,
+    Position, ValueError,
+};
+use crate::full_almost_zero_copy::{parse_value_with_config, StringOrStr};
+use crate::grammar::{self, ValueKind};
+use crate::{parser, KeyValueParser, Span};
 
-fn eat_value(input: &str) -> IResult<&str, ()> {
-    let (_, peek_next_char) = take(1usize)(input)?;
-
-    match peek_next_char {
-        "\"" => eat_quoted_value(input),
-        _ => eat_unquoted_value(input),
-    }
-}
-
-fn eat_unquoted_value(input: &str) -> IResult<&str, ()> {
-    let (input, _) = take_while(|c: char| !c.is_whitespace())(input)?;
+/// Skips over a raw value, quotes and all.  Escapes are checked but not decoded.
+pub(crate) fn eat_value<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, ()> {
+    let (input, _) = match grammar::value_kind(input, config)? {
+        (input, ValueKind::Quoted | ValueKind::TripleQuoted) => {
+            return eat_quoted_value(input, config)
+        }
+        (input, ValueKind::Literal) => grammar::literal_value(input)?,
+        (input, ValueKind::Heredoc) => {
+            let (input, (text, _)) = grammar::heredoc_value(input)?;
+            (input, text)
+        }
+        (input, ValueKind::Unquoted) => grammar::unquoted_value(input, config)?,
+    };
     Ok((input, ()))
 }
 
-fn eat_quoted_value(input: &str) -> IResult<&str, ()> {
-    let (input, _) = tag("\"")(input)?;
+fn eat_quoted_value<'a>(input: &'a str, config: &ParserConfig) -> NomResult<'a, ()> {
+    let start = input;
+    let (input, close) = grammar::open_quote(input)?;
 
     let mut head = input;
     loop {
@@ -32,18 +44,23 @@ fn eat_quoted_value(input: &str) -> IResult<&str, ()> {
         let (input, _) = take_while(|c: char| c != '\\' && c != '"')(head)?;
 
         // let's see what we hit
-        let (data, backslash_or_quote) = take(1usize)(input)?;
+        let (data, backslash_or_quote) =
+            expect(start, ParseError::UnterminatedQuote, take(1usize)(input))?;
 
         match backslash_or_quote {
-            "\"" => {
-                // we hit a quote
+            "\"" if input.starts_with(close) => {
+                // we hit the closing quote
                 // so we're done
-                return Ok((data, ()));
+                return Ok((&input[close.len()..], ()));
+            }
+            "\"" => {
+                // a quote in triple quotes is part of the value
+                head = data;
             }
             _ => {
                 // we hit a backslash
-                // so we need to take what's next
-                let (data, _) = take(1usize)(data)?;
+                // so we need to check the escape it starts
+                let (data, _) = grammar::escape(input, config.strict_escapes)?;
 
                 // move the head forward
                 head = data;
@@ -52,48 +69,444 @@ fn eat_quoted_value(input: &str) -> IResult<&str, ()> {
     }
 }
 
+/// Reads the next key and skips over its value.  Returns the key and the input starting at its value,
+/// or no value if the key is a flag.
+fn next_pair<'a>(
+    input: &'a str,
+    config: &ParserConfig,
+) -> NomResult<'a, (&'a str, Option<&'a str>)> {
+    // get next key
+    let input = grammar::skip_space(input, config);
+    if let Some((input, key)) = grammar::flag(input, config) {
+        let (input, _) = grammar::delimiter(input, config)?;
+        return Ok((input, (key, None)));
+    }
+    let (value, key) = grammar::key_and_separator(input, config)?;
 
-pub fn nom_parse<'a>(input: &'a str, search_key: &str) -> IResult<&'a str,StringOrStr<'a>> {
-    let mut head = input.trim_start();
+    // eat the value, and the delimiter with the whitespace and comments around it
+    let (input, _) = eat_value(value, config)?;
+    let (input, _) = grammar::delimiter(input, config)?;
+
+    Ok((input, (key, Some(value))))
+}
+
+/// Walks the key value pairs and flags of the input in order, without decoding any values.
+struct Pairs<'a, 'c> {
+    head: &'a str,
+    config: &'c ParserConfig,
+}
+impl<'a, 'c> Pairs<'a, 'c> {
+    fn new(input: &'a str, config: &'c ParserConfig) -> Self {
+        Self {
+            head: grammar::skip_space(input, config),
+            config,
+        }
+    }
+}
+impl<'a> Iterator for Pairs<'a, '_> {
+    type Item = Result<(&'a str, Option<&'a str>), nom::Err<NomError<'a>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.head.is_empty() {
+            return None;
+        }
+        match next_pair(self.head, self.config) {
+            Ok((input, pair)) => {
+                self.head = input;
+                Some(Ok(pair))
+            }
+            Err(e) => {
+                // stop at the first error
+                self.head = "";
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Finds the key and raw value of `search_key`, following the duplicate policy.  Returns
+/// `None` if the key is not in the input.
+///
+/// Under [`DuplicatePolicy::LastWins`] the scan goes on after the key is found, to look for
+/// later pairs with the same key, but input that does not parse after it is not an error.
+/// Only [`DuplicatePolicy::Error`] validates the whole input.
+fn find_pair<'a>(
+    input: &'a str,
+    search_key: &str,
+    config: &ParserConfig,
+) -> NomResult<'a, Option<(&'a str, &'a str)>> {
+    let policy = config.duplicate_policy;
+    let mut found = None;
+    let mut seen = HashSet::new();
+
+    for pair in Pairs::new(input, config) {
+        let pair = match pair {
+            Ok(pair) => pair,
+            // the key was found, and nothing after it is needed to tell its value
+            Err(_) if found.is_some() && policy == DuplicatePolicy::LastWins => break,
+            Err(e) => return Err(e),
+        };
+        // flags are not pairs, so the policy does not apply to them
+        let (key, Some(value)) = pair else { continue };
+
+        if policy == DuplicatePolicy::Error && !seen.insert(key) {
+            return fail(
+                &input[grammar::offset_in(input, key)..],
+                ParseError::DuplicateKey,
+            );
+        }
 
-    while !head.is_empty() {
-        // get next key
-        let (input, _) = multispace0(head)?;
-        let (input, key) =
-            take_while(|c: char| c.is_alphanumeric() || c == '-' || c == '_')(input)?;
-        let (input, _) = multispace0(input)?;
-        let (input, _) = tag("=")(input)?;
-    
-        // Found the key, extract the key, profit!
         if key == search_key {
-            let (_,res) = parse_value(input)?;
-            return Ok((input,res));
-        } else {
-            // eat the next value
-            let (input,_) = eat_value(input)?;
-            head = input;
+            found = Some((key, value));
+
+            // the first value wins, there is no need to look any further
+            if matches!(
+                policy,
+                DuplicatePolicy::FirstWins | DuplicatePolicy::Collect
+            ) {
+                break;
+            }
+        }
+    }
+
+    Ok((input, found))
+}
+
+/// Finds the value of `search_key`.  Returns `None` if the key is not in the input.
+pub fn nom_parse<'a>(input: &'a str, search_key: &str) -> NomResult<'a, Option<StringOrStr<'a>>> {
+    nom_parse_with_config(input, search_key, &ParserConfig::default())
+}
+
+/// Finds the value of `search_key` with the given configuration.  Returns `None` if the key
+/// is not in the input.
+pub fn nom_parse_with_config<'a>(
+    input: &'a str,
+    search_key: &str,
+    config: &ParserConfig,
+) -> NomResult<'a, Option<StringOrStr<'a>>> {
+    match find_pair(input, search_key, config)? {
+        // Found the key, extract the key, profit!
+        (_, Some((_, value))) => {
+            let (_, res) = parse_value_with_config(value, config)?;
+            Ok((value, Some(res)))
         }
+        (_, None) => Ok((input, None)),
+    }
+}
+
+/// Finds the value of `search_key`.  A key that is not in the input is a
+/// [`ValueError::Missing`], and input that does not parse up to the key a
+/// [`ValueError::Parse`].
+/// ```
+/// use key_value_parser::{zero_parse, ValueError};
+/// assert_eq!(zero_parse::parse("port=8080", "port").unwrap(), "8080");
+/// assert!(matches!(
+///     zero_parse::parse("port=8080", "host"),
+///     Err(ValueError::Missing { .. })
+/// ));
+/// ```
+pub fn parse<'a>(input: &'a str, search_key: &str) -> Result<StringOrStr<'a>, ValueError> {
+    parse_with_config(input, search_key, &ParserConfig::default())
+}
+
+/// Finds the value of `search_key` with the given configuration, see [`parse`].
+pub fn parse_with_config<'a>(
+    input: &'a str,
+    search_key: &str,
+    config: &ParserConfig,
+) -> Result<StringOrStr<'a>, ValueError> {
+    match nom_parse_with_config(input, search_key, config) {
+        Ok((_, Some(value))) => Ok(value),
+        Ok((_, None)) => Err(ValueError::Missing {
+            key: search_key.to_string(),
+        }),
+        Err(e) => Err(ValueError::Parse {
+            key: search_key.to_string(),
+            error: to_parse_error(input, e),
+        }),
     }
+}
 
-    Err(nom::Err::Error(nom::error::Error::new(
-        input,
-        nom::error::ErrorKind::Fail,
-    )))
+/// Returns true if `search_key` appears on its own, without a value, under
+/// [`ParserConfig::flags`].
+/// ```
+/// use key_value_parser::{config::ParserConfig, zero_parse};
+/// let config = ParserConfig::new().flags(true);
+/// assert!(zero_parse::is_flag("quiet ro debug=1", "ro", &config).unwrap());
+/// assert!(!zero_parse::is_flag("quiet ro debug=1", "debug", &config).unwrap());
+/// ```
+pub fn is_flag(input: &str, search_key: &str, config: &ParserConfig) -> Result<bool, ParseError> {
+    for pair in Pairs::new(input, config) {
+        match pair {
+            Ok((key, None)) if key == search_key => return Ok(true),
+            Ok(_) => {}
+            Err(e) => return Err(to_parse_error(input, e)),
+        }
+    }
+    Ok(false)
 }
 
-pub fn parse<'a>(input: &'a str, search_key: &str) -> Result<StringOrStr<'a>> {
-    let res = nom_parse(input,search_key).finish();
-    match res {
-        Ok((_,value)) => Ok(value),
-        Err(e) => match e.code {
-            nom::error::ErrorKind::Fail => Err(anyhow::anyhow!("Key not found")),
-            _ => Err(anyhow::anyhow!("Error parsing input: {:?}",e)),
+/// Finds the value of `search_key` and parses it as a `T`.  The value is parsed straight from
+/// the input unless it had to be unescaped.
+/// ```
+/// use key_value_parser::{zero_parse, ValueError};
+/// assert_eq!(zero_parse::parse_as::<u16>("port=8080", "port").unwrap(), 8080);
+/// assert!(matches!(
+///     zero_parse::parse_as::<u16>("port=http", "port"),
+///     Err(ValueError::Invalid { .. })
+/// ));
+/// assert!(matches!(
+///     zero_parse::parse_as::<u16>("port=\"8080", "port"),
+///     Err(ValueError::Parse { .. })
+/// ));
+/// ```
+pub fn parse_as<T>(input: &str, search_key: &str) -> Result<T, ValueError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parse_as_with_config(input, search_key, &ParserConfig::default())
+}
+
+/// Finds the value of `search_key` with the given configuration and parses it as a `T`, see
+/// [`parse_as`].
+pub fn parse_as_with_config<T>(
+    input: &str,
+    search_key: &str,
+    config: &ParserConfig,
+) -> Result<T, ValueError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let value = parse_with_config(input, search_key, config)?;
+    parse_as_type(search_key, &value)
+}
+
+/// A parser that holds on to nothing but the input.
+///
+/// The input is validated once when the parser is constructed, and every lookup rescans it.
+/// Nothing is allocated unless a value contains escape characters.
+///
+/// Since there is no map behind it, it cannot implement `Index` (there is nothing to borrow a
+/// decoded value from), nor `FromIterator` and `Extend` (there is nothing to add pairs to).
+#[derive(Debug, Clone)]
+pub struct Parser<'a> {
+    input: &'a str,
+    config: ParserConfig,
+}
+impl<'a> Parser<'a> {
+    /// Construct a new parser.
+    /// If the parser cannot parse the input, an error will be returned.
+    /// ```
+    /// use key_value_parser::zero_parse::Parser;
+    /// const DATA: &str = "  key = value   ";
+    /// let parser = Parser::new(DATA).unwrap();
+    /// assert_eq!(parser.len(), 1);
+    /// assert_eq!(parser.get("key").unwrap().as_ref(), "value");
+    /// ```
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        Self::with_config(input, &ParserConfig::default())
+    }
+
+    /// Construct a new parser with the given configuration.
+    /// If the parser cannot parse the input, an error will be returned.
+    pub fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        let mut seen = HashSet::new();
+        for pair in Pairs::new(input, config) {
+            let (key, value) = pair.map_err(|e| to_parse_error(input, e))?;
+
+            if value.is_some()
+                && config.duplicate_policy == DuplicatePolicy::Error
+                && !seen.insert(key)
+            {
+                let offset = grammar::offset_in(input, key);
+                return Err(ParseError::DuplicateKey(Position::new(input, offset)));
+            }
+        }
+
+        Ok(Self {
+            input,
+            config: config.clone(),
+        })
+    }
+
+    /// Gets a value from the input.  The value is only decoded when it is found.
+    pub fn get(&self, key: &str) -> Option<StringOrStr<'a>> {
+        // the input was validated in new, so neither the scan nor the value can fail
+        let (_, pair) = find_pair(self.input, key, &self.config).ok()?;
+        parse_value_with_config(pair?.1, &self.config)
+            .ok()
+            .map(|(_, value)| value)
+    }
+
+    /// Gets where the pair of a key sits in the input.
+    pub fn get_span(&self, key: &str) -> Option<Span> {
+        let (_, pair) = find_pair(self.input, key, &self.config).ok()?;
+        Span::of(self.input, pair?.0, &self.config)
+    }
+
+    /// Returns true if the key appears on its own, without a value, under
+    /// [`ParserConfig::flags`](crate::config::ParserConfig::flags)
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.flags().any(|k| k == key)
+    }
+
+    /// Iterates over the distinct keys that appear on their own, in the order they first appear
+    pub fn flags(&self) -> impl Iterator<Item = &'a str> + '_ {
+        let mut seen = HashSet::new();
+        Pairs::new(self.input, &self.config)
+            .filter_map(Result::ok)
+            .filter(|(_, v)| v.is_none())
+            .map(|(k, _)| k)
+            .filter(move |k| seen.insert(*k))
+    }
+
+    /// Gets every value of a key, in the order they appear in the input.
+    pub fn get_all<'s>(&'s self, key: &'s str) -> impl Iterator<Item = StringOrStr<'a>> + 's
+    where
+        'a: 's,
+    {
+        Pairs::new(self.input, &self.config)
+            .filter_map(Result::ok)
+            .filter(move |(k, _)| *k == key)
+            .filter_map(|(_, v)| {
+                parse_value_with_config(v?, &self.config)
+                    .ok()
+                    .map(|(_, v)| v)
+            })
+    }
+
+    /// Returns how many distinct keys are available.  Counting them takes one scan of the
+    /// input.
+    pub fn len(&self) -> usize {
+        self.keys().count()
+    }
+
+    /// Returns true if there are no key value pairs
+    pub fn is_empty(&self) -> bool {
+        self.keys().next().is_none()
+    }
+
+    /// Iterates over the distinct keys in the order they first appear
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+        let mut seen = HashSet::new();
+        Pairs::new(self.input, &self.config)
+            .filter_map(Result::ok)
+            .filter(|(_, v)| v.is_some())
+            .map(|(k, _)| k)
+            .filter(move |k| seen.insert(*k))
+    }
+
+    /// Iterates over the key value pairs in the order the keys first appear.  Each value is
+    /// the one [`Parser::get`] returns.  The input is scanned once, and each value is decoded
+    /// as it is reached.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, StringOrStr<'a>)> + '_ {
+        self.raw_pairs().into_iter().filter_map(|(k, v)| {
+            parse_value_with_config(v, &self.config)
+                .ok()
+                .map(|(_, v)| (k, v))
+        })
+    }
+
+    /// Reads the key and raw value of every pair in one scan, in the order the keys first
+    /// appear.  Each key keeps the value the duplicate policy picks.
+    fn raw_pairs(&self) -> Vec<(&'a str, &'a str)> {
+        let mut pairs = Vec::new();
+        let mut index = HashMap::new();
+        for (key, value) in Pairs::new(self.input, &self.config).filter_map(Result::ok) {
+            // flags are not pairs
+            let Some(value) = value else { continue };
+            match index.entry(key) {
+                Entry::Vacant(e) => {
+                    e.insert(pairs.len());
+                    pairs.push((key, value));
+                }
+                Entry::Occupied(e) if self.config.duplicate_policy == DuplicatePolicy::LastWins => {
+                    pairs[*e.get()].1 = value;
+                }
+                Entry::Occupied(_) => {}
+            }
         }
+        pairs
+    }
+
+    /// Iterates over the values, in the order their keys first appear
+    pub fn values(&self) -> impl Iterator<Item = StringOrStr<'a>> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+}
+
+impl<'s, 'a> IntoIterator for &'s Parser<'a> {
+    type Item = (&'a str, StringOrStr<'a>);
+    type IntoIter = Box<dyn Iterator<Item = (&'a str, StringOrStr<'a>)> + 's>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl<'a> IntoIterator for Parser<'a> {
+    type Item = (&'a str, StringOrStr<'a>);
+    type IntoIter = Box<dyn Iterator<Item = (&'a str, StringOrStr<'a>)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let pairs = self.raw_pairs();
+        Box::new(pairs.into_iter().filter_map(move |(k, v)| {
+            parse_value_with_config(v, &self.config)
+                .ok()
+                .map(|(_, v)| (k, v))
+        }))
+    }
+}
+
+/// Two parsers are equal if they hold the same keys with the same values, however the input
+/// was laid out.
+impl PartialEq for Parser<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        let other: HashMap<_, _> = other.iter().collect();
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(&v))
+    }
+}
+impl Eq for Parser<'_> {}
+
+impl<'a> KeyValueParser<'a> for Parser<'a> {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
+        Parser::new(input)
+    }
+
+    fn with_config(input: &'a str, config: &ParserConfig) -> Result<Self, ParseError> {
+        Parser::with_config(input, config)
+    }
+
+    fn get(&self, key: &str) -> Option<StringOrStr<'_>> {
+        Parser::get(self, key)
+    }
+
+    fn get_span(&self, key: &str) -> Option<Span> {
+        Parser::get_span(self, key)
+    }
+
+    fn is_flag(&self, key: &str) -> bool {
+        Parser::is_flag(self, key)
+    }
+
+    fn len(&self) -> usize {
+        Parser::len(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        Parser::is_empty(self)
+    }
+
+    fn iter(&self) -> parser::Iter<'_> {
+        Box::new(Parser::iter(self))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::config::Delimiter;
     use crate::full_almost_zero_copy;
 
     use super::*;
@@ -127,4 +540,137 @@ mod tests {
 
         assert!(parse(DATA, "four").is_err());
     }
+
+    #[test]
+    fn test_parser() {
+        const DATA: &str = "one=1 two = 2 one=3 escaped=\"a \\\"b\\\"\"";
+
+        let parser = Parser::new(DATA).unwrap();
+        assert_eq!(parser.len(), 3);
+        assert_eq!(parser.get("one").unwrap().as_ref(), "3");
+        assert_eq!(parser.get("two").unwrap().as_ref(), "2");
+        assert_eq!(parser.get("escaped").unwrap().as_ref(), "a \"b\"");
+        assert!(parser.get("three").is_none());
+        assert_eq!(parser.keys().collect::<Vec<_>>(), ["one", "two", "escaped"]);
+        assert_eq!(
+            parser.iter().collect::<Vec<_>>(),
+            [
+                ("one", "3".into()),
+                ("two", "2".into()),
+                ("escaped", "a \"b\"".into())
+            ]
+        );
+
+        let config = ParserConfig::new().duplicate_policy(DuplicatePolicy::FirstWins);
+        let first = Parser::with_config(DATA, &config).unwrap();
+        assert_eq!(first.values().next().unwrap().as_ref(), "1");
+        assert_ne!(first, parser);
+
+        assert!(Parser::new("one=1 two").is_err());
+
+        let value = parse("literal='a \"b\" \\n'", "literal").unwrap();
+        assert!(matches!(value, StringOrStr::Str("a \"b\" \\n")));
+    }
+
+    #[test]
+    fn test_trailing_garbage_after_the_key() {
+        const DATA: &str = "a=1 b=2 a=3 ;garbage";
+        assert_eq!(parse("a=1 ;garbage", "a").unwrap().as_ref(), "1");
+        assert_eq!(parse(DATA, "a").unwrap().as_ref(), "3");
+        assert!(parse(DATA, "c").is_err());
+
+        let config = ParserConfig::new().duplicate_policy(DuplicatePolicy::FirstWins);
+        assert_eq!(parse_with_config(DATA, "a", &config).unwrap().as_ref(), "1");
+        // only the error policy has to look at every pair
+        let config = ParserConfig::new().duplicate_policy(DuplicatePolicy::Error);
+        assert!(parse_with_config("a=1 ;garbage", "a", &config).is_err());
+    }
+
+    #[test]
+    fn test_parse_as() {
+        assert_eq!(parse_as::<u8>("a=1 b=x", "a"), Ok(1));
+        assert!(matches!(
+            parse_as::<u8>("a=1 b=x", "b"),
+            Err(ValueError::Invalid { key, .. }) if key == "b"
+        ));
+        assert!(matches!(
+            parse_as::<u8>("a=1", "b"),
+            Err(ValueError::Missing { key }) if key == "b"
+        ));
+        let error = parse_as::<u8>("a=1 b", "b").unwrap_err();
+        assert_eq!(error.key(), "b");
+        assert!(matches!(
+            &error,
+            ValueError::Parse { error: ParseError::MissingEquals(p), .. } if p.offset == 5
+        ));
+        assert_eq!(
+            error.to_string(),
+            "cannot look up `b`: expected separator after key at line 1, column 6"
+        );
+
+        let config = ParserConfig::new().separator(':');
+        assert_eq!(
+            parse_as_with_config::<u16>("port: 80", "port", &config),
+            Ok(80)
+        );
+        assert!(parse_as::<u16>("port: 80", "port").is_err());
+    }
+
+    #[test]
+    fn test_lookups_in_other_dialects() {
+        assert_eq!(
+            parse("one=1 # note\nthree=#kept", "three")
+                .unwrap()
+                .as_ref(),
+            "#kept"
+        );
+
+        let config = ParserConfig::new()
+            .delimiter(Delimiter::Char('&'))
+            .space_around_separator(false)
+            .quotes(&[]);
+        assert_eq!(
+            parse_with_config("q=a&page=2", "page", &config)
+                .unwrap()
+                .as_ref(),
+            "2"
+        );
+
+        let config = ParserConfig::new().key_chars(&['.', ':']);
+        const KEYS: &str = "http.status=200 user:id=7";
+        assert_eq!(
+            parse_with_config(KEYS, "user:id", &config)
+                .unwrap()
+                .as_ref(),
+            "7"
+        );
+
+        const FLAGS: &str = "quiet ro debug=1 root = /dev/sda1 quiet";
+        let config = ParserConfig::new().flags(true);
+        assert!(is_flag(FLAGS, "ro", &config).unwrap());
+        assert!(!is_flag(FLAGS, "root", &config).unwrap());
+        assert_eq!(
+            parse_with_config(FLAGS, "debug", &config).unwrap().as_ref(),
+            "1"
+        );
+        let parser = Parser::with_config(FLAGS, &config).unwrap();
+        assert_eq!(parser.flags().collect::<Vec<_>>(), ["quiet", "ro"]);
+    }
+
+    #[test]
+    fn test_std_traits() {
+        let parser = Parser::new("one=1 two=\"2\\\"\"").unwrap();
+
+        // equality looks at the pairs, not at how the input was written
+        assert_eq!(parser, Parser::new("two = \"2\\\"\"   one=1").unwrap());
+        assert_ne!(parser, Parser::new("one=1").unwrap());
+        assert_eq!(parser.clone(), parser);
+
+        assert_eq!(parser.values().collect::<Vec<_>>(), ["1", "2\""]);
+        assert_eq!((&parser).into_iter().count(), 2);
+        assert_eq!(
+            parser.into_iter().collect::<Vec<_>>(),
+            [("one", "1".into()), ("two", "2\"".into())]
+        );
+    }
 }